@@ -0,0 +1,24 @@
+language-name = Deutsch
+
+start-msg =
+    Hallo. Dies ist ein digitales Liederbuch. :)
+    Befehle:
+    /list - Listet alle Lieder auf
+    /language - Ändert die Sprache
+    { $folders }Ansonsten tippe einfach den Titel oder Teile des Titels des Liedes ein und du bekommst dein Lied zugeschickt.
+song-not-found = Kein Lied mit diesem Titel gefunden.
+page = Seite
+list-expired = Diese Liste ist abgelaufen. Bitte suche erneut.
+language-msg = Welche Sprache soll der Bot sprechen?
+language-set = Der Bot spricht jetzt Deutsch.
+
+report-msg = Bitte sende einen gefundenen Fehler entweder als Text oder Sprachnachricht.
+report-error = Das hat nicht funktioniert. Versuche es nochmal oder /cancel.
+report-success = Deine Korrektur wurde erfolgreich gemeldet.
+report-cancel = Das Fehlermelden wurde abgebrochen.
+
+command-start = Zeigt die Hilfe an
+command-list = Listet alle Lieder auf
+command-report = Meldet einen Fehler in einem Lied
+command-language = Ändert die Sprache
+command-folder = Lieder im Ordner { $folder }
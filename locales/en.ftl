@@ -0,0 +1,27 @@
+# The name of the language in the language itself, shown by /language.
+language-name = English
+
+# { $folders } is replaced by the commands of the folders, one per line.
+start-msg =
+    Hello. This is a digital song book. :)
+    Commands:
+    /list - Lists all songs
+    /language - Changes the language
+    { $folders }Otherwise simply type the title or parts of the title of the song and you will receive the song.
+song-not-found = Didn't find any song with this title.
+page = Page
+list-expired = This list has expired. Please search again.
+language-msg = Which language should the bot speak?
+language-set = The bot speaks English now.
+
+report-msg = Please send an error you found either as text or voice message.
+report-error = That didn't work. Try again or /cancel.
+report-success = Successfully reported your correction.
+report-cancel = Reporting canceled.
+
+# Descriptions of the commands telegram suggests when typing "/".
+command-start = Shows the help
+command-list = Lists all songs
+command-report = Reports an error in a song
+command-language = Changes the language
+command-folder = Songs in the folder { $folder }
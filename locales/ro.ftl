@@ -0,0 +1,24 @@
+language-name = Română
+
+start-msg =
+    Salut! Această e o carte de cântari digitală. :)
+    Comenzi:
+    /list - Listează toate cântările
+    /language - Schimbă limba
+    { $folders }Deasemenea puteți introduce titlul sau cuvinte din titlul cântării iar bot-ul va găsi piesa corespondentă.
+song-not-found = Niciun cântec găsit cu acest nume
+page = Pagina
+list-expired = Această listă a expirat. Căutați din nou.
+language-msg = Ce limbă să vorbească bot-ul?
+language-set = Bot-ul vorbește acum română.
+
+report-msg = Vă rugăm să trimiteți eroare pe care ați găsit-o fie ca mesaj text sau vocal.
+report-error = Asta nu a mers. Încercați din nou sau /cancel
+report-success = A raportat corect corectia.
+report-cancel = Raportarea a fost anulată.
+
+command-start = Afișează ajutorul
+command-list = Listează toate cântările
+command-report = Raportează o eroare într-o cântare
+command-language = Schimbă limba
+command-folder = Cântările din dosarul { $folder }
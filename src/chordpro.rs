@@ -0,0 +1,239 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::search_file::{self, SongRecord};
+
+pub fn is_chordpro_file(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => {
+            let ext = ext.to_lowercase();
+            ext == "cho" || ext == "chordpro"
+        }
+        None => false,
+    }
+}
+
+/*
+ * The name is the file name without extension and
+ * is used as title if there's no {title} directive.
+*/
+pub fn parse(name: &str, content: &str) -> SongRecord {
+    let mut song = SongRecord {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    let mut lyrics: Vec<String> = vec![];
+    let mut skipped_env: Option<&str> = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('{') && line.ends_with('}') {
+            let (mut name, mut value) = parse_directive(line);
+            if let Some(env) = skipped_env {
+                if is_end_of(&name, env) {
+                    skipped_env = None;
+                }
+                continue;
+            }
+            if name == "meta" {
+                (name, value) = split_directive(&value);
+            }
+            match name.as_str() {
+                "title" | "t" => {
+                    if song.title.is_empty() {
+                        song.title = value;
+                    }
+                }
+                "subtitle" | "st" | "sorttitle" => song.alternative_titles.push(value),
+                "artist" | "lyricist" => set_if_none(&mut song.author, value),
+                "composer" => set_if_none(&mut song.composer, value),
+                "key" => set_if_none(&mut song.key, value),
+                "tempo" => set_if_none(&mut song.tempo, value),
+                "language" => set_if_none(&mut song.language, value),
+                "hymnal_number" | "number" => set_if_none(&mut song.hymnal_number, value),
+                "tag" | "tags" => song.tags.push(value),
+                _ => skipped_env = skipped_environment(&name),
+            }
+            continue;
+        }
+        if skipped_env.is_some() {
+            continue;
+        }
+        let line = strip_chords(line);
+        if !line.trim().is_empty() {
+            lyrics.push(line.trim().to_string());
+        }
+    }
+    if song.title.is_empty() {
+        song.title = name.to_string();
+    }
+    song.lyrics = lyrics.join("\n");
+    song
+}
+
+fn set_if_none(field: &mut Option<String>, value: String) {
+    if field.is_none() && !value.is_empty() {
+        *field = Some(value);
+    }
+}
+
+/*
+ * Directives are either written as
+ * {name: value} or {name value}.
+*/
+fn parse_directive(line: &str) -> (String, String) {
+    split_directive(&line[1..line.len() - 1])
+}
+
+/*
+ * {meta: name value} is the generic
+ * way of writing {name: value}.
+*/
+fn split_directive(inner: &str) -> (String, String) {
+    let inner = inner.trim();
+    let is_separator = |c: char| c == ':' || c.is_whitespace();
+    match inner.find(is_separator) {
+        Some(i) => (
+            inner[..i].to_lowercase(),
+            inner[i..].trim_start_matches(is_separator).to_string(),
+        ),
+        None => (inner.to_lowercase(), String::new()),
+    }
+}
+
+/*
+ * Environments whose content isn't
+ * singable text, e.g. guitar tabs,
+ * chord grids or embedded notation.
+*/
+fn skipped_environment(name: &str) -> Option<&'static str> {
+    match name {
+        "start_of_tab" | "sot" => Some("tab"),
+        "start_of_grid" | "sog" => Some("grid"),
+        "start_of_abc" => Some("abc"),
+        "start_of_ly" => Some("ly"),
+        "start_of_svg" => Some("svg"),
+        _ => None,
+    }
+}
+
+fn is_end_of(name: &str, env: &str) -> bool {
+    match name {
+        "eot" => env == "tab",
+        "eog" => env == "grid",
+        _ => name == "end_of_".to_owned() + env,
+    }
+}
+
+fn strip_chords(line: &str) -> String {
+    let mut res = String::new();
+    let mut in_chord = false;
+    for c in line.chars() {
+        match c {
+            '[' => in_chord = true,
+            ']' if in_chord => in_chord = false,
+            _ if !in_chord => res.push(c),
+            _ => {}
+        }
+    }
+    res
+}
+
+/*
+ * Creates the content of the (version 2) search
+ * file from all chordpro files in the given folder.
+*/
+pub fn create_search_file(songs_path: &str) -> String {
+    let mut paths = vec![];
+    read_chordpro_files(Path::new(songs_path), &mut paths);
+    // the same order as the songs of the index
+    paths.sort_by_key(|path| {
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        (file_name.to_lowercase(), path.clone())
+    });
+    let mut songs: Vec<SongRecord> = vec![];
+    for path in paths {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) => {
+                eprintln!("Cannot read {}.", path.display());
+                dbg!(err);
+                continue;
+            }
+        };
+        let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+        songs.push(parse(&name, &content));
+    }
+    search_file::write(&songs)
+}
+
+fn read_chordpro_files(folder_path: &Path, paths: &mut Vec<PathBuf>) {
+    let read_dir = match fs::read_dir(folder_path) {
+        Ok(read_dir) => read_dir,
+        Err(err) => {
+            eprintln!("Cannot read the folder {}.", folder_path.display());
+            dbg!(err);
+            return;
+        }
+    };
+    for dir_entry in read_dir.flatten() {
+        let path = dir_entry.path();
+        if path.is_dir() {
+            read_chordpro_files(&path, paths);
+        } else if is_chordpro_file(&path) {
+            paths.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn title_is_taken_from_the_directive() {
+        let song = super::parse("Amazing_Grace", "{title: Amazing Grace}\n{t: Other}\n");
+        assert_eq!(song.title, "Amazing Grace");
+        let song = super::parse("Amazing_Grace", "{t Amazing Grace}\n");
+        assert_eq!(song.title, "Amazing Grace");
+        let song = super::parse("Amazing_Grace", "Amazing grace\n");
+        assert_eq!(song.title, "Amazing_Grace");
+    }
+
+    #[test]
+    fn meta_directives_are_read_like_the_others() {
+        let song = super::parse(
+            "Song",
+            "{meta: artist John Newton}\n{meta: key G}\n{composer: unknown}\n{tag: hymn}\n",
+        );
+        assert_eq!(song.author.as_deref(), Some("John Newton"));
+        assert_eq!(song.key.as_deref(), Some("G"));
+        assert_eq!(song.composer.as_deref(), Some("unknown"));
+        assert_eq!(song.tags, vec!["hymn"]);
+    }
+
+    #[test]
+    fn chords_are_removed_from_the_lyrics() {
+        let song = super::parse(
+            "Song",
+            "# a comment\n[G]Amazing [G7]grace, how [C]sweet\n\n[D] \n",
+        );
+        assert_eq!(song.lyrics, "Amazing grace, how sweet");
+    }
+
+    #[test]
+    fn tabs_and_grids_are_skipped() {
+        let song = super::parse(
+            "Song",
+            concat!(
+                "first\n",
+                "{sot}\ne|---0---|\n{eot}\n",
+                "second\n",
+                "{start_of_grid}\n| G . | C . |\n{end_of_grid}\n",
+                "{start_of_tab: Intro}\nB|---1---|\n{end_of_tab}\n",
+                "third\n",
+            ),
+        );
+        assert_eq!(song.lyrics, "first\nsecond\nthird");
+    }
+}
@@ -0,0 +1,64 @@
+use frankenstein::api_params::SetMyCommandsParams;
+use frankenstein::objects::BotCommand;
+
+use crate::i18n::{CommandMsgs, I18n};
+use crate::slug;
+use crate::HandleArg;
+
+/*
+ * Sets the commands telegram suggests when typing "/",
+ * once for every language and once in --lang for everyone else.
+*/
+pub fn register(args: &HandleArg) {
+    let mut params = SetMyCommandsParams::builder()
+        .commands(get_commands(args, &args.i18n.commands))
+        .build();
+    set_my_commands(args, &params);
+    // users whose telegram is set to a supported language see it in their language
+    for (lang, _) in args.translations.languages() {
+        let i18n = I18n::new(&args.translations, &lang, &args.index.folder_names);
+        params.commands = get_commands(args, &i18n.commands);
+        params.language_code = Some(lang);
+        set_my_commands(args, &params);
+    }
+}
+
+fn get_commands(args: &HandleArg, msgs: &CommandMsgs) -> Vec<BotCommand> {
+    let mut commands = vec![
+        get_command("start", &msgs.start),
+        get_command("list", &msgs.list),
+        get_command("language", &msgs.language),
+    ];
+    if args.reports_path.is_some() {
+        commands.push(get_command("report", &msgs.report));
+    }
+    for name in &args.index.folder_names {
+        if is_valid_command(name) {
+            commands.push(get_command(name, &msgs.folder(name)));
+        }
+    }
+    commands
+}
+
+/*
+ * Commands can be typed in any case, but the suggested
+ * ones have to be lowercase or telegram refuses them.
+ * Other folders still work, they just aren't suggested.
+*/
+fn is_valid_command(name: &str) -> bool {
+    slug::is_valid_command(name) && !name.chars().any(|c| c.is_ascii_uppercase())
+}
+
+fn get_command(command: &str, description: &str) -> BotCommand {
+    BotCommand::builder()
+        .command(command)
+        .description(description)
+        .build()
+}
+
+fn set_my_commands(args: &HandleArg, params: &SetMyCommandsParams) {
+    if let Err(err) = args.messenger.set_my_commands(params) {
+        eprintln!("set_my_commands failed.");
+        dbg!(err);
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::Utc;
+use frankenstein::api_params::SendMessageParams;
+use frankenstein::ChatId;
+use serde::{Deserialize, Serialize};
+
+use crate::HandleArg;
+
+/*
+ * A dialog is forgotten if the user
+ * doesn't answer within this time.
+*/
+const TIMEOUT_SECS: i64 = 10 * 60;
+
+/*
+ * Flows that take more than one message.
+ * A new flow needs a variant here and a match
+ * arm in handle_message and cancel_msg.
+*/
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum DialogKind {
+    Report,
+}
+
+/*
+ * What a dialog wants after handling a message.
+*/
+pub enum Step {
+    Stay,
+    Done,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Dialog {
+    chat_id: i64,
+    user_id: u64,
+    kind: DialogKind,
+    // unix time of the last message, for the timeout
+    updated: i64,
+}
+
+/*
+ * The open dialogs, one per user and chat.
+*/
+#[derive(Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct Dialogs {
+    dialogs: Vec<Dialog>,
+}
+
+impl Dialogs {
+    pub fn start(&mut self, chat_id: i64, user_id: u64, kind: DialogKind) {
+        self.end(chat_id, user_id);
+        self.dialogs.push(Dialog {
+            chat_id,
+            user_id,
+            kind,
+            updated: Utc::now().timestamp(),
+        });
+    }
+
+    pub fn end(&mut self, chat_id: i64, user_id: u64) {
+        self.dialogs
+            .retain(|dialog| dialog.chat_id != chat_id || dialog.user_id != user_id);
+    }
+
+    fn get(&mut self, chat_id: i64, user_id: u64) -> Option<DialogKind> {
+        let now = Utc::now().timestamp();
+        self.dialogs
+            .retain(|dialog| now - dialog.updated < TIMEOUT_SECS);
+        let dialog = self
+            .dialogs
+            .iter_mut()
+            .find(|dialog| dialog.chat_id == chat_id && dialog.user_id == user_id)?;
+        dialog.updated = now;
+        Some(dialog.kind)
+    }
+}
+
+/*
+ * Passes the message to the open dialog of its
+ * user in its chat. Commands end every dialog,
+ * all but /cancel are then handled as usual.
+ * Returns false if the message is left to the caller.
+*/
+pub fn handle_message(args: &HandleArg) -> bool {
+    let msg = args.msg.as_ref().unwrap();
+    let Some(user) = msg.from.as_ref() else {
+        return false;
+    };
+    let kind = crate::lock(&args.state).dialogs.get(msg.chat.id, user.id);
+    let Some(kind) = kind else {
+        return false;
+    };
+    if let Some(text) = msg.text.as_deref().filter(|text| text.starts_with('/')) {
+        let mut params = SendMessageParams::builder()
+            .chat_id(ChatId::Integer(msg.chat.id))
+            .text(cancel_msg(args, kind))
+            .build();
+        crate::send_message(args.messenger.as_ref(), &mut params);
+        end_dialog(args, msg.chat.id, user.id);
+        return crate::strip_bot_username(args, text) == Some("/cancel");
+    }
+    let step = match kind {
+        DialogKind::Report => crate::handle_report(args),
+    };
+    if let Step::Done = step {
+        end_dialog(args, msg.chat.id, user.id);
+    }
+    true
+}
+
+fn end_dialog(args: &HandleArg, chat_id: i64, user_id: u64) {
+    let mut state = crate::lock(&args.state);
+    state.dialogs.end(chat_id, user_id);
+    state.save();
+}
+
+fn cancel_msg(args: &HandleArg, kind: DialogKind) -> String {
+    match kind {
+        DialogKind::Report => args.i18n.report.cancel_msg.clone(),
+    }
+}
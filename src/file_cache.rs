@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::state;
+
+const FILE_CACHE_FILE: &str = "file_ids.json";
+
+// a file is considered changed if either changes
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub struct Fingerprint {
+    modified: SystemTime,
+    size: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedFile {
+    file_id: String,
+    fingerprint: Fingerprint,
+}
+
+/*
+ * The file_ids telegram assigned to the uploaded songs.
+ * Sending the file_id instead of the file saves the upload.
+ * A file_id is only used as long as the song file
+ * has the same mtime and size as when it was uploaded.
+*/
+#[derive(Serialize, Deserialize, Default)]
+pub struct FileCache {
+    files: HashMap<PathBuf, CachedFile>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl FileCache {
+    pub fn load(data_path: Option<&String>) -> Self {
+        let (mut cache, path) = state::load_json::<Self>(data_path, FILE_CACHE_FILE);
+        cache.path = path;
+        cache
+    }
+
+    pub fn get(&self, song_path: &Path) -> Option<String> {
+        let cached = self.files.get(song_path)?;
+        if get_fingerprint(song_path)? != cached.fingerprint {
+            return None;
+        }
+        Some(cached.file_id.clone())
+    }
+
+    pub fn insert(&mut self, song_path: &Path, file_id: String) {
+        let Some(fingerprint) = get_fingerprint(song_path) else {
+            return;
+        };
+        self.files.insert(
+            song_path.to_path_buf(),
+            CachedFile {
+                file_id,
+                fingerprint,
+            },
+        );
+        self.save();
+    }
+
+    pub fn remove(&mut self, song_path: &Path) {
+        if self.files.remove(song_path).is_some() {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        if let Some(path) = self.path.as_ref() {
+            state::save_json(path, self);
+        }
+    }
+}
+
+pub fn get_fingerprint(path: &Path) -> Option<Fingerprint> {
+    let metadata = fs::metadata(path).ok()?;
+    Some(Fingerprint {
+        modified: metadata.modified().ok()?,
+        size: metadata.len(),
+    })
+}
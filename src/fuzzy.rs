@@ -0,0 +1,48 @@
+/*
+ * Similarity between 0 and 1 of the query and
+ * the best matching part of the text.
+ * 1 means the query is contained in the text,
+ * every typo lowers it by 1/<query length>.
+*/
+pub fn similarity(query: &str, text: &str) -> f64 {
+    let query: Vec<char> = query.chars().collect();
+    if query.is_empty() {
+        return 0.0;
+    }
+    let distance = substring_distance(&query, text);
+    1.0 - distance as f64 / query.len() as f64
+}
+
+/*
+ * Similarity of the worst matching term, because
+ * every term has to be found in the text.
+*/
+pub fn similarity_of_all(terms: &[String], text: &str) -> f64 {
+    terms
+        .iter()
+        .map(|term| similarity(term, text))
+        .reduce(f64::min)
+        .unwrap_or(0.0)
+}
+
+/*
+ * Smallest edit distance (levenshtein) between
+ * the query and any substring of the text.
+ * Like the regular levenshtein algorithm except that
+ * the match may start and end anywhere in the text.
+*/
+fn substring_distance(query: &[char], text: &str) -> usize {
+    let mut prev: Vec<usize> = (0..=query.len()).collect();
+    let mut cur: Vec<usize> = vec![0; query.len() + 1];
+    let mut best = query.len();
+    for t in text.chars() {
+        cur[0] = 0;
+        for i in 1..=query.len() {
+            let cost = if query[i - 1] == t { 0 } else { 1 };
+            cur[i] = (prev[i - 1] + cost).min(prev[i] + 1).min(cur[i - 1] + 1);
+        }
+        best = best.min(cur[query.len()]);
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    best
+}
@@ -1,4 +1,127 @@
+use std::collections::HashMap;
 use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+/*
+ * The translations that are built in. English
+ * is used for everything a translation misses.
+*/
+const EMBEDDED: [(&str, &str); 3] = [
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("ro", include_str!("../locales/ro.ftl")),
+];
+
+const FALLBACK_LANG: &str = "en";
+
+type Messages = HashMap<String, String>;
+
+/*
+ * The messages of every language by their key.
+ * A folder of <lang>.ftl files can override
+ * single messages or add whole languages.
+*/
+pub struct Translations {
+    // in the order /language offers them
+    languages: Vec<(String, Messages)>,
+}
+
+impl Translations {
+    pub fn load(translations_path: Option<&String>) -> Self {
+        let mut translations = Self { languages: vec![] };
+        for (lang, content) in EMBEDDED {
+            let messages = parse_or_exit(&format!("locales/{}.ftl", lang), content);
+            translations.add(lang, messages);
+        }
+        let Some(translations_path) = translations_path else {
+            return translations;
+        };
+        let read_dir = match fs::read_dir(translations_path) {
+            Ok(read_dir) => read_dir,
+            Err(err) => {
+                eprintln!("Cannot read translations path {}.", translations_path);
+                dbg!(err);
+                process::exit(-1);
+            }
+        };
+        let mut paths: Vec<PathBuf> = read_dir
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "ftl"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let lang = path.file_stem().unwrap().to_string_lossy().to_string();
+            let content = match fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(err) => {
+                    eprintln!("Cannot read translation {}.", path.display());
+                    dbg!(err);
+                    process::exit(-1);
+                }
+            };
+            let messages = parse_or_exit(&path.display().to_string(), &content);
+            translations.add(&lang, messages);
+        }
+        translations
+    }
+
+    // the languages with their name in the language itself
+    pub fn languages(&self) -> Vec<(String, String)> {
+        self.languages
+            .iter()
+            .map(|(lang, _)| (lang.clone(), self.get(lang, "language-name", &[])))
+            .collect()
+    }
+
+    /*
+     * The supported language of a telegram language code like "de-AT".
+     */
+    pub fn get_supported_lang(&self, language_code: &str) -> Option<String> {
+        let code = language_code.split('-').next().unwrap().to_lowercase();
+        self.languages
+            .iter()
+            .map(|(lang, _)| lang)
+            .find(|lang| **lang == code)
+            .cloned()
+    }
+
+    fn add(&mut self, lang: &str, messages: Messages) {
+        match self.languages.iter_mut().find(|(l, _)| l == lang) {
+            Some((_, existing)) => existing.extend(messages),
+            None => self.languages.push((lang.to_string(), messages)),
+        }
+    }
+
+    fn get_messages(&self, lang: &str) -> Option<&Messages> {
+        let find = |lang: &str| {
+            self.languages
+                .iter()
+                .find(|(l, _)| l == lang)
+                .map(|(_, messages)| messages)
+        };
+        // moldovan is written like romanian
+        match lang {
+            "md" => find(lang).or_else(|| find("ro")),
+            _ => find(lang),
+        }
+    }
+
+    /*
+     * The message with its placeholders filled in,
+     * in english if the language doesn't have it.
+     */
+    fn get(&self, lang: &str, key: &str, args: &[(&str, &str)]) -> String {
+        let message = [lang, FALLBACK_LANG]
+            .iter()
+            .find_map(|lang| self.get_messages(lang)?.get(key));
+        match message {
+            Some(message) => fill(message, args),
+            None => key.to_string(),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct ReportMsgs {
@@ -8,134 +131,222 @@ pub struct ReportMsgs {
     pub cancel_msg: String,
 }
 
+/*
+ * Descriptions of the commands telegram
+ * suggests when typing "/".
+*/
+#[derive(Clone)]
+pub struct CommandMsgs {
+    pub start: String,
+    pub list: String,
+    pub report: String,
+    pub language: String,
+    // with a placeholder for the name of the folder
+    folder: String,
+}
+
+impl CommandMsgs {
+    pub fn folder(&self, name: &str) -> String {
+        fill(&self.folder, &[("folder", name)])
+    }
+}
+
 #[derive(Clone)]
 pub struct I18n {
-    lang: String,
     pub start_msg: String,
     pub song_not_found: String,
+    pub page: String,
+    pub list_expired: String,
+    pub language_msg: String,
+    // confirms the choice, in the chosen language
+    pub language_set: String,
     pub report: ReportMsgs,
+    pub commands: CommandMsgs,
 }
 
 impl I18n {
-    pub fn new(lang: String, songs_path: String) -> Self {
-        match lang.as_str() {
-            "de" => Self {
-                lang,
-                start_msg: String::from(format!(
-                    "Hallo. Dies ist ein digitales Liederbuch. :)\n\
-						Befehle:\n\
-						/list - Listet alle Lieder auf\n\
-						{}\
-						Ansonsten tippe einfach den Titel oder Teile des Titels \
-						des Liedes ein und du bekommst dein Lied zugeschickt.",
-                    get_commands(songs_path).as_str()
-                )),
-                song_not_found: String::from("Kein Lied mit diesem Titel gefunden."),
-                report: ReportMsgs {
-                    msg: String::from(
-                        "Bitte sende einen gefundenen Fehler \
-							entweder als Text oder Sprachnachricht.",
-                    ),
-                    error_msg: String::from(
-                        "Das hat nicht funktioniert. Versuche es nochmal oder /cancel.",
-                    ),
-                    success_msg: String::from("Deine Korrektur wurde erfolgreich gemeldet."),
-                    cancel_msg: String::from("Das Fehlermelden wurde abgebrochen."),
-                },
-            },
-            "ro" | "md" => Self {
-                lang,
-                start_msg: String::from(format!(
-                    "Salut! Această e o carte de cântari digitală. :)\n\
-						Comenzi:\n\
-						/list - Listează toate cântările\n\
-						{}\
-						Deasemenea puteți introduce titlul sau cuvinte din titlul \
-						cântării iar bot-ul va găsi piesa corespondentă.",
-                    get_commands(songs_path).as_str()
-                )),
-                song_not_found: String::from("Niciun cântec găsit cu acest nume"),
-                report: ReportMsgs {
-                    msg: String::from(
-                        "Vă rugăm să trimiteți eroare pe care \
-							ați găsit-o fie ca mesaj text sau vocal.",
-                    ),
-                    error_msg: String::from("Asta nu a mers. Încercați din nou sau /cancel"),
-                    success_msg: String::from("A raportat corect corectia."),
-                    cancel_msg: String::from("Raportarea a fost anulată."),
-                },
+    pub fn new(translations: &Translations, lang: &str, folder_names: &[String]) -> Self {
+        let get = |key: &str| translations.get(lang, key, &[]);
+        let folders = get_commands(folder_names);
+        Self {
+            start_msg: translations.get(lang, "start-msg", &[("folders", &folders)]),
+            song_not_found: get("song-not-found"),
+            page: get("page"),
+            list_expired: get("list-expired"),
+            language_msg: get("language-msg"),
+            language_set: get("language-set"),
+            report: ReportMsgs {
+                msg: get("report-msg"),
+                error_msg: get("report-error"),
+                success_msg: get("report-success"),
+                cancel_msg: get("report-cancel"),
             },
-            _ => Self {
-                lang,
-                start_msg: String::from(format!(
-                    "Hello. This is a digital song book. :)\n\
-						Commands:\n\
-						/list - Lists all songs\n\
-						{}\
-						Otherwise simply type the title or parts of the title \
-						of the song and you will receive the song.",
-                    get_commands(songs_path).as_str()
-                )),
-                song_not_found: String::from("Didn't find any song with this title."),
-                report: ReportMsgs {
-                    msg: String::from(
-                        "Please send an error you found \
-							either as text or voice message.",
-                    ),
-                    error_msg: String::from("That didn't work. Try again or /cancel."),
-                    success_msg: String::from("Successfully reported your correction."),
-                    cancel_msg: String::from("Reporting canceled."),
-                },
+            commands: CommandMsgs {
+                start: get("command-start"),
+                list: get("command-list"),
+                report: get("command-report"),
+                language: get("command-language"),
+                folder: get("command-folder"),
             },
         }
     }
-    pub fn format(&self, name: &String) -> String {
-        let mut formatted_name = name.to_string();
-        match self.lang.as_str() {
-            "de" => {
-                formatted_name = formatted_name.replace("Ö", "Oe");
-                formatted_name = formatted_name.replace("ö", "oe");
-                formatted_name = formatted_name.replace("Ü", "Ue");
-                formatted_name = formatted_name.replace("ü", "ue");
-                formatted_name = formatted_name.replace("Ä", "Ae");
-                formatted_name = formatted_name.replace("ä", "ae");
-                formatted_name = formatted_name.replace("ß", "ss");
+}
+
+/*
+ * Reads the part of the fluent syntax the translations need:
+ * comments, "key = message", messages continued on indented
+ * lines and { $variable } placeables. Everything else fluent
+ * has, like terms, attributes and selectors, is an error.
+ * Returns the line number and what's wrong with it on errors.
+*/
+fn parse(content: &str) -> Result<Messages, (usize, String)> {
+    let mut messages = Messages::new();
+    let mut key: Option<String> = None;
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with('#') {
+            key = None;
+        } else if line.starts_with(char::is_whitespace) {
+            let Some(message) = key.as_ref().and_then(|key| messages.get_mut(key)) else {
+                return Err((i + 1, String::from("indented line outside of a message")));
+            };
+            let text = line.trim();
+            if text.starts_with('.') {
+                return Err((i + 1, String::from("attributes aren't supported")));
             }
-            "md" => {
-                formatted_name = formatted_name.replace("ă", "a");
-                formatted_name = formatted_name.replace("â", "a");
-                formatted_name = formatted_name.replace("î", "i");
-                formatted_name = formatted_name.replace("ș", "s");
-                formatted_name = formatted_name.replace("ț", "t");
+            if text.starts_with('[') || text.starts_with("*[") {
+                return Err((i + 1, String::from("variants aren't supported")));
             }
-            _ => {}
+            check_placeables(text).map_err(|err| (i + 1, err))?;
+            if !message.is_empty() {
+                message.push('\n');
+            }
+            message.push_str(text);
+        } else {
+            let Some((name, message)) = line.split_once('=') else {
+                return Err((i + 1, String::from("expected \"key = message\"")));
+            };
+            let name = name.trim().to_string();
+            if name.starts_with('-') {
+                return Err((i + 1, String::from("terms aren't supported")));
+            }
+            if !is_identifier(&name) {
+                return Err((i + 1, format!("\"{}\" isn't a valid key", name)));
+            }
+            let message = message.trim();
+            check_placeables(message).map_err(|err| (i + 1, err))?;
+            messages.insert(name.clone(), message.to_string());
+            key = Some(name);
         }
-        formatted_name = formatted_name.replace("-", "_");
-        formatted_name = formatted_name.replace(" ", "_");
-        return formatted_name;
     }
+    Ok(messages)
 }
 
-fn get_commands(songs_path: String) -> String {
+/*
+ * Only variables can be placed into messages,
+ * a selector would span several lines.
+*/
+fn check_placeables(text: &str) -> Result<(), String> {
+    let mut rest = text;
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err(String::from("\"}\" without \"{\""));
+        }
+        let Some(len) = rest[start..].find('}') else {
+            return Err(String::from(
+                "\"{\" without \"}\" in the same line, selectors aren't supported",
+            ));
+        };
+        let placeable = rest[start + 1..start + len].trim();
+        if !placeable.strip_prefix('$').is_some_and(is_identifier) {
+            return Err(format!(
+                "\"{{ {} }}\" isn't supported, only {{ $variable }}",
+                placeable
+            ));
+        }
+        rest = &rest[start + len + 1..];
+    }
+    Ok(())
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn parse_or_exit(name: &str, content: &str) -> Messages {
+    match parse(content) {
+        Ok(messages) => messages,
+        Err((line, message)) => {
+            eprintln!("{}:{}: {}", name, line, message);
+            process::exit(-1);
+        }
+    }
+}
+
+/*
+ * Replaces the placeholders like { $folder },
+ * unknown ones are left as they are.
+*/
+fn fill(message: &str, args: &[(&str, &str)]) -> String {
+    let mut filled = String::new();
+    let mut rest = message;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let placeholder = &rest[start..start + len + 1];
+        let name = placeholder[1..len].trim().trim_start_matches('$');
+        filled.push_str(&rest[..start]);
+        match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) => filled.push_str(value),
+            None => filled.push_str(placeholder),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+fn get_commands(folder_names: &[String]) -> String {
     let mut commands: String = String::new();
-    for name in get_folder_names(&songs_path) {
+    for name in folder_names {
         commands.push_str(&("/".to_owned() + name.as_str() + "\n"));
     }
-    return commands;
+    commands
 }
 
-pub fn get_folder_names(songs_path: &String) -> Vec<String> {
-    let songs_dir = fs::read_dir(songs_path).unwrap();
-    let mut folder_names: Vec<String> = vec![];
-    let mut is_dir: bool;
-    let mut dir_entry;
-    for f in songs_dir {
-        dir_entry = f.expect("Error: f");
-        is_dir = dir_entry.file_type().unwrap().is_dir();
-        if is_dir {
-            let name: String = dir_entry.file_name().to_str().unwrap().to_string();
-            folder_names.push(name)
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn translations_are_parsed() {
+        let messages = super::parse(
+            "# comment\nstart-msg =\n    Hello\n    { $folders }Bye\npage = Page { $n }\n",
+        )
+        .unwrap();
+        assert_eq!(messages["start-msg"], "Hello\n{ $folders }Bye");
+        assert_eq!(messages["page"], "Page { $n }");
+    }
+
+    #[test]
+    fn unsupported_fluent_syntax_is_an_error() {
+        let errors = [
+            ("-brand = Songy\n", 1),
+            ("page = Page\n    .title = Title\n", 2),
+            ("page = { $n ->\n    [one] Page\n   *[other] Pages\n}\n", 1),
+            ("page = { -brand }\n", 1),
+            ("page = { NUMBER($n) }\n", 1),
+            ("page = Page\n    [one] Page\n", 2),
+            ("page = Page }\n", 1),
+            ("page number = Page\n", 1),
+        ];
+        for (content, line) in errors {
+            let result = super::parse(content);
+            assert_eq!(result.err().map(|(i, _)| i), Some(line), "{}", content);
         }
     }
-    return folder_names;
 }
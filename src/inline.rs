@@ -0,0 +1,83 @@
+use frankenstein::api_params::{AnswerInlineQueryParams, InlineQueryResult};
+use frankenstein::objects::{
+    InlineQuery, InlineQueryResultArticle, InlineQueryResultCachedDocument, InputMessageContent,
+    InputTextMessageContent,
+};
+
+use crate::song_index::SongFile;
+use crate::HandleArg;
+
+/*
+ * Telegram allows at most 50 results per answer.
+ * More are requested with the next_offset
+ * when the user scrolls down.
+*/
+const MAX_RESULTS: usize = 50;
+
+/*
+ * @songybot <search text> in any chat.
+ * Without search text all songs are listed.
+*/
+pub fn handle_inline_query(args: &HandleArg, query: &InlineQuery) {
+    let offset: usize = query.offset.parse().unwrap_or(0);
+    let songs = if query.query.trim().is_empty() {
+        args.index.songs.clone()
+    } else {
+        crate::search_songs(args, &query.query)
+    };
+    let results: Vec<InlineQueryResult> = songs
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(MAX_RESULTS)
+        .map(|(i, song)| get_result(args, i, song))
+        .collect();
+    let mut params = AnswerInlineQueryParams::builder()
+        .inline_query_id(query.id.clone())
+        .results(results)
+        .build();
+    if songs.len() > offset + MAX_RESULTS {
+        params.next_offset = Some((offset + MAX_RESULTS).to_string());
+    }
+    if let Err(err) = args.messenger.answer_inline_query(&params) {
+        eprintln!("answer_inline_query failed.");
+        dbg!(err);
+    }
+}
+
+/*
+ * Songs that have been sent before already have a
+ * file_id and can be sent directly as document.
+ * All others are sent as command, the bot then
+ * answers with the song if it's a member of the chat.
+*/
+fn get_result(args: &HandleArg, i: usize, song: &SongFile) -> InlineQueryResult {
+    let file_id = crate::lock(&args.file_cache).get(&song.path);
+    if let Some(file_id) = file_id {
+        return InlineQueryResult::CachedDocument(
+            InlineQueryResultCachedDocument::builder()
+                .id(i.to_string())
+                .title(song.description().unwrap_or(song.name().to_string()))
+                .document_file_id(file_id)
+                .build(),
+        );
+    }
+    let mut command = String::from("/");
+    command.push_str(&song.slug);
+    if let Some(username) = args.bot_username.as_ref() {
+        command.push('@');
+        command.push_str(username);
+    }
+    InlineQueryResult::Article(
+        InlineQueryResultArticle::builder()
+            .id(i.to_string())
+            .title(song.description().unwrap_or(song.name().to_string()))
+            .description(command.clone())
+            .input_message_content(InputMessageContent::Text(
+                InputTextMessageContent::builder()
+                    .message_text(command)
+                    .build(),
+            ))
+            .build(),
+    )
+}
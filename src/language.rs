@@ -0,0 +1,94 @@
+use frankenstein::api_params::{AnswerCallbackQueryParams, ReplyMarkup, SendMessageParams};
+use frankenstein::objects::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, User};
+use frankenstein::ChatId;
+
+use crate::i18n::I18n;
+use crate::HandleArg;
+
+const CALLBACK_PREFIX: &str = "lang:";
+
+/*
+ * The language the bot speaks with the user: the one
+ * chosen with /language, otherwise the one telegram
+ * is set to if it's supported, otherwise --lang.
+*/
+pub fn get_lang(args: &HandleArg, user: &User) -> String {
+    let chosen = crate::lock(&args.state).languages.get(&user.id).cloned();
+    // the translation of a chosen language may have been removed since
+    let chosen = chosen.and_then(|lang| args.translations.get_supported_lang(&lang));
+    let lang = chosen.or_else(|| {
+        user.language_code
+            .as_deref()
+            .and_then(|code| args.translations.get_supported_lang(code))
+    });
+    lang.unwrap_or_else(|| args.lang.clone())
+}
+
+/*
+ * Asks for the language with a button for each one.
+*/
+pub fn send_choice(args: &HandleArg, chat_id: i64) {
+    let buttons: Vec<InlineKeyboardButton> = args
+        .translations
+        .languages()
+        .into_iter()
+        .map(|(lang, name)| {
+            InlineKeyboardButton::builder()
+                .text(name)
+                .callback_data(format!("{}{}", CALLBACK_PREFIX, lang))
+                .build()
+        })
+        .collect();
+    let keyboard = InlineKeyboardMarkup::builder()
+        .inline_keyboard(vec![buttons])
+        .build();
+    let mut params = SendMessageParams::builder()
+        .chat_id(ChatId::Integer(chat_id))
+        .text(args.i18n.language_msg.clone())
+        .reply_markup(ReplyMarkup::InlineKeyboardMarkup(keyboard))
+        .build();
+    crate::send_message(args.messenger.as_ref(), &mut params);
+}
+
+// whether the button is one of send_choice
+pub fn is_choice(query: &CallbackQuery) -> bool {
+    query
+        .data
+        .as_deref()
+        .is_some_and(|data| data.starts_with(CALLBACK_PREFIX))
+}
+
+/*
+ * Stores the language of a pressed button.
+*/
+pub fn handle_callback_query(args: &HandleArg, query: &CallbackQuery) {
+    let answer_params = AnswerCallbackQueryParams::builder()
+        .callback_query_id(query.id.clone())
+        .build();
+    if let Err(err) = args.messenger.answer_callback_query(&answer_params) {
+        eprintln!("answer_callback_query failed.");
+        dbg!(err);
+    }
+    let code = query
+        .data
+        .as_deref()
+        .and_then(|data| data.strip_prefix(CALLBACK_PREFIX))
+        .unwrap_or_default();
+    let Some(lang) = args.translations.get_supported_lang(code) else {
+        return;
+    };
+    let mut state = crate::lock(&args.state);
+    state.languages.insert(query.from.id, lang.clone());
+    state.save();
+    drop(state);
+    let i18n = I18n::new(&args.translations, &lang, &args.index.folder_names);
+    let chat_id: i64 = match query.message.as_ref() {
+        Some(msg) => msg.chat.id,
+        None => query.from.id.try_into().unwrap(),
+    };
+    let mut params = SendMessageParams::builder()
+        .chat_id(ChatId::Integer(chat_id))
+        .text(i18n.language_set)
+        .build();
+    crate::send_message(args.messenger.as_ref(), &mut params);
+}
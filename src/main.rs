@@ -1,25 +1,54 @@
 use bytes::Bytes;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use frankenstein::api_params::DeleteWebhookParams;
 use frankenstein::api_params::File;
-use frankenstein::api_params::GetFileParams;
 use frankenstein::api_params::InputFile;
 use frankenstein::api_params::SendDocumentParams;
 use frankenstein::objects::AllowedUpdate;
-use frankenstein::objects::UpdateContent;
+use frankenstein::objects::{Update, UpdateContent};
 use frankenstein::Api;
 use frankenstein::ChatId;
 use frankenstein::GetUpdatesParams;
 use frankenstein::Message;
 use frankenstein::SendMessageParams;
 use frankenstein::TelegramApi;
-use std::collections::VecDeque;
-use std::fs::DirEntry;
-use std::io::Write;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::{fs, process, thread, time};
+mod chordpro;
+mod commands;
+mod dialog;
+mod file_cache;
+mod fuzzy;
 mod i18n;
+mod inline;
+mod language;
+mod normalize;
+mod pagination;
+mod pdf;
+mod search_file;
+mod slug;
+mod snippet;
+mod song_index;
+mod state;
+#[cfg(test)]
+mod tests;
+mod title_cache;
+mod transport;
+mod webhook;
+mod workers;
 use config_file::FromConfigFile;
-use i18n::I18n;
+use dialog::{DialogKind, Step};
+use file_cache::FileCache;
+use i18n::{I18n, Translations};
+use normalize::Normalizer;
+use pagination::{ListEntry, SongLists};
 use serde::Deserialize;
+use song_index::{SongFile, SongIndex};
+use state::State;
+use transport::{Messenger, Telegram};
+use workers::Workers;
 
 /*
  * 4096 is the max character length
@@ -30,6 +59,19 @@ use serde::Deserialize;
 */
 const MAX_TEXT_LEN: usize = 4096;
 
+/*
+ * Longer titles are cut in lists, so that together with the
+ * snippets (see snippet::MAX_SNIPPET_LEN) a page of a list
+ * always fits into one message.
+*/
+const MAX_DESCRIPTION_LEN: usize = 100;
+
+const ALLOWED_UPDATES: [AllowedUpdate; 3] = [
+    AllowedUpdate::Message,
+    AllowedUpdate::InlineQuery,
+    AllowedUpdate::CallbackQuery,
+];
+
 #[derive(Parser, Debug, Deserialize)]
 struct Config {
     #[arg(short, long, help = "telegram bot api token")]
@@ -42,12 +84,77 @@ struct Config {
         help = "language that the bot speaks: 'en', 'de' or 'ro/md'"
     )]
     lang: Option<String>,
+    #[arg(
+        long,
+        help = "path to folder with <lang>.ftl files that override the built-in translations or add languages"
+    )]
+    translations_path: Option<String>,
     #[arg(short = 'f', long, help = "path to search file")]
     search_file: Option<String>,
     #[arg(short, long, help = "path to folder where reports will be saved")]
     reports_path: Option<String>,
     #[arg(short, long, help = "path to yml config file")]
     config: Option<String>,
+    #[arg(
+        long,
+        help = "enables typo tolerant search: minimum similarity between 0 and 1 of a match, e.g. 0.75"
+    )]
+    fuzzy_cutoff: Option<f64>,
+    #[arg(
+        long,
+        help = "base url of the telegram bot api, e.g. of a self-hosted telegram-bot-api server, defaults to https://api.telegram.org"
+    )]
+    api_url: Option<String>,
+    #[arg(
+        long,
+        help = "receive updates via webhook instead of polling: public https url the reverse proxy forwards to --webhook-listen"
+    )]
+    webhook_url: Option<String>,
+    #[arg(
+        long,
+        help = "address the webhook server listens on, defaults to 127.0.0.1:8080"
+    )]
+    webhook_listen: Option<String>,
+    #[arg(
+        long,
+        help = "secret telegram sends with every webhook request, generated on every start if not set"
+    )]
+    webhook_secret: Option<String>,
+    #[arg(
+        long,
+        help = "path to folder where songy keeps its state between restarts"
+    )]
+    data_path: Option<String>,
+    #[arg(
+        long,
+        help = "number of updates that are handled at the same time, defaults to 4"
+    )]
+    workers: Option<usize>,
+    #[arg(
+        long,
+        help = "find songs with cyrillic titles by typing latin letters and the other way around: 'true' or 'false'"
+    )]
+    transliterate_cyrillic: Option<bool>,
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "create the search file from a folder of chordpro files")]
+    Index {
+        #[arg(help = "path to folder with chordpro files")]
+        songs_path: String,
+        #[arg(
+            short,
+            long,
+            help = "path the search file is written to, defaults to --search-file or stdout"
+        )]
+        output: Option<String>,
+    },
+    #[command(about = "list the song files that can't be sent by their file name")]
+    Check,
 }
 
 impl Config {
@@ -56,9 +163,19 @@ impl Config {
             token: None,
             songs_path: None,
             lang: None,
+            translations_path: None,
             search_file: None,
             reports_path: None,
             config: None,
+            fuzzy_cutoff: None,
+            api_url: None,
+            webhook_url: None,
+            webhook_listen: None,
+            webhook_secret: None,
+            data_path: None,
+            workers: None,
+            transliterate_cyrillic: None,
+            command: None,
         }
     }
 }
@@ -67,32 +184,39 @@ struct SongNotFound {
     message: String,
 }
 
+/*
+ * Every update is handled with its own clone,
+ * everything that is shared between the
+ * updates lives behind an Arc.
+*/
+#[derive(Clone)]
 struct HandleArg {
-    api: Api,
+    messenger: Arc<dyn Messenger>,
     msg: Option<Message>,
-    token: String,
     reports_path: Option<String>,
+    // --lang, for users without a language of their own
+    lang: String,
+    translations: Arc<Translations>,
+    // in the language of the user of the update
     i18n: I18n,
-    songs_path: String,
-    search_file: Option<String>,
+    index: Arc<SongIndex>,
+    fuzzy_cutoff: Option<f64>,
+    bot_username: Option<String>,
+    // file_ids of the songs already sent, they are also used in inline results
+    file_cache: Arc<Mutex<FileCache>>,
+    song_lists: Arc<Mutex<SongLists>>,
+    state: Arc<Mutex<State>>,
 }
 
 struct HandleResult {
-    // wait_for_report: bool,
-    user_id_waiting_for_report: Option<u64>,
+    start_dialog: Option<DialogKind>,
 }
 
-enum OutgoingTextMsg {
-    DirEntry(Vec<DirEntry>),
-    String(Vec<String>),
-}
-
-struct FindSongArgs {
-    songs_path: String,
-    i18n: I18n,
+struct FindSongArgs<'a> {
+    index: &'a SongIndex,
     search_string: String,
     search_type: SearchType,
-    search_file: String,
+    fuzzy_cutoff: Option<f64>,
 }
 
 #[derive(Debug)]
@@ -104,6 +228,8 @@ enum SearchType {
 struct SearchResult {
     ss_in_title: Vec<String>,
     ss_in_lyrics: Vec<String>,
+    // the matched parts of the lyrics by the name of the song
+    snippets: HashMap<String, String>,
 }
 
 enum ReportFileType {
@@ -112,65 +238,48 @@ enum ReportFileType {
 }
 
 fn main() {
-    let config = get_config();
-    let api = Api::new(&config.token.clone().unwrap().as_str());
-    let is_reports_path = config.reports_path.is_some();
-    let songs_path: String = add_ending_slash(config.songs_path.unwrap());
-    let mut handle_arg = HandleArg {
-        api: api.clone(),
-        msg: None,
-        token: config.token.unwrap().clone(),
-        reports_path: config.reports_path.clone(),
-        i18n: I18n::new(config.lang.unwrap(), songs_path.clone()),
-        songs_path: songs_path.clone(),
-        search_file: config.search_file.clone(),
-    };
+    let mut config = get_config();
+    if let Some(command) = config.command.take() {
+        run_command(command, &config);
+        return;
+    }
+    let token = config.token.clone().unwrap();
+    let api_url = config.api_url.clone().unwrap();
+    let api_url = api_url.trim_end_matches('/');
+    let api = Api::new_url(format!("{}/bot{}", api_url, token));
+    let messenger = Arc::new(Telegram::new(api.clone(), token, api_url));
+    let state = State::load(config.data_path.as_ref());
+    let mut handle_arg = get_handle_arg(&config, messenger, get_bot_username(&api), state);
+    print_warnings(&handle_arg.index);
+    commands::register(&handle_arg);
+    let workers = Workers::new(config.workers.unwrap());
+    if config.webhook_url.is_some() {
+        webhook::run(&api, &mut handle_arg, &workers, &config);
+    } else {
+        poll_updates(&api, &mut handle_arg, &workers);
+    }
+}
+
+fn poll_updates(api: &Api, handle_arg: &mut HandleArg, workers: &Workers) {
+    // getUpdates doesn't work as long as a webhook is set
+    if let Err(err) = api.delete_webhook(&DeleteWebhookParams::builder().build()) {
+        eprintln!("delete_webhook failed.");
+        dbg!(err);
+    }
     let mut updates_params = GetUpdatesParams::builder()
-        .allowed_updates(vec![AllowedUpdate::Message])
+        .allowed_updates(ALLOWED_UPDATES.to_vec())
         .build();
-    let mut handle_res: Option<HandleResult>;
-    let mut user_ids_waiting_for_report = vec![];
     loop {
         let dur = time::Duration::from_millis(500);
         thread::sleep(dur);
-        let result = TelegramApi::get_updates(&api, &updates_params);
+        refresh_index(handle_arg);
+        updates_params.offset = lock(&handle_arg.state).offset;
+        let result = TelegramApi::get_updates(api, &updates_params);
         match result {
             Ok(val) => {
-                for update in &val.result {
-                    updates_params.offset = Some(i64::from(update.update_id) + 1);
-                    match &update.content {
-                        UpdateContent::Message(msg) => {
-                            handle_arg.msg = Some(msg.clone());
-                            if is_reports_path && !user_ids_waiting_for_report.is_empty() {
-                                let user_id = msg.from.as_ref().unwrap().id;
-                                let mut remove_later: Option<usize> = None;
-                                let mut skip = false;
-                                for (i, id) in user_ids_waiting_for_report.iter().enumerate() {
-                                    if user_id == *id {
-                                        skip = true;
-                                        if handle_report(&handle_arg) {
-                                            remove_later = Some(i);
-                                        }
-                                    }
-                                }
-                                if let Some(id) = remove_later {
-                                    user_ids_waiting_for_report.swap_remove(id);
-                                }
-                                if skip {
-                                    continue;
-                                }
-                            }
-                            if msg.text.is_some() {
-                                handle_res = handle_text_message(&handle_arg);
-                                if let Some(res) = handle_res {
-                                    if let Some(id) = res.user_id_waiting_for_report {
-                                        user_ids_waiting_for_report.push(id);
-                                    }
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
+                // the offset only moves on once the updates are handled
+                for update in val.result {
+                    workers.dispatch(handle_arg.clone(), update);
                 }
             }
             Err(_err) => {
@@ -180,6 +289,139 @@ fn main() {
     }
 }
 
+/*
+ * The updates already handled are
+ * still answered from the old index.
+*/
+fn refresh_index(handle_arg: &mut HandleArg) {
+    if let Some(index) = handle_arg.index.refreshed() {
+        let folders_changed = index.folder_names != handle_arg.index.folder_names;
+        print_warnings(&index);
+        handle_arg.index = Arc::new(index);
+        if folders_changed {
+            commands::register(handle_arg);
+        }
+    }
+}
+
+fn print_warnings(index: &SongIndex) {
+    for warning in &index.warnings {
+        eprintln!("{}", warning);
+    }
+}
+
+fn handle_update(handle_arg: &mut HandleArg, update: &Update) {
+    let user = match &update.content {
+        UpdateContent::Message(msg) => msg.from.as_deref(),
+        UpdateContent::InlineQuery(query) => Some(&query.from),
+        UpdateContent::CallbackQuery(query) => Some(&query.from),
+        _ => None,
+    };
+    let lang = match user {
+        Some(user) => language::get_lang(handle_arg, user),
+        None => handle_arg.lang.clone(),
+    };
+    handle_arg.i18n = I18n::new(
+        &handle_arg.translations,
+        &lang,
+        &handle_arg.index.folder_names,
+    );
+    match &update.content {
+        UpdateContent::Message(msg) => {
+            let text = msg.text.as_deref();
+            if text.is_some_and(|text| strip_bot_username(handle_arg, text).is_none()) {
+                // the command is for another bot in the group
+                return;
+            }
+            handle_arg.msg = Some(msg.clone());
+            if dialog::handle_message(handle_arg) {
+                return;
+            }
+            if msg.text.is_some() {
+                let handle_res = handle_text_message(handle_arg);
+                if let (Some(res), Some(user)) = (handle_res, msg.from.as_ref()) {
+                    if let Some(kind) = res.start_dialog {
+                        let mut state = lock(&handle_arg.state);
+                        state.dialogs.start(msg.chat.id, user.id, kind);
+                        state.save();
+                    }
+                }
+            }
+        }
+        UpdateContent::InlineQuery(query) => {
+            inline::handle_inline_query(handle_arg, query);
+        }
+        UpdateContent::CallbackQuery(query) => {
+            if language::is_choice(query) {
+                language::handle_callback_query(handle_arg, query);
+            } else {
+                pagination::handle_callback_query(handle_arg, query);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn get_handle_arg(
+    config: &Config,
+    messenger: Arc<dyn Messenger>,
+    bot_username: Option<String>,
+    state: State,
+) -> HandleArg {
+    let songs_path: String = add_ending_slash(config.songs_path.clone().unwrap());
+    let index = SongIndex::new(
+        &songs_path,
+        config.search_file.as_ref(),
+        config.data_path.as_ref(),
+        get_normalizer(config),
+    );
+    let translations = Translations::load(config.translations_path.as_ref());
+    HandleArg {
+        messenger,
+        msg: None,
+        reports_path: config.reports_path.clone(),
+        lang: config.lang.clone().unwrap(),
+        i18n: I18n::new(
+            &translations,
+            config.lang.as_ref().unwrap(),
+            &index.folder_names,
+        ),
+        translations: Arc::new(translations),
+        index: Arc::new(index),
+        fuzzy_cutoff: config.fuzzy_cutoff,
+        bot_username,
+        file_cache: Arc::new(Mutex::new(FileCache::load(config.data_path.as_ref()))),
+        song_lists: Arc::new(Mutex::new(SongLists::new())),
+        state: Arc::new(Mutex::new(state)),
+    }
+}
+
+/*
+ * A panic while a lock is held poisons it. The data
+ * is used anyway, otherwise one failed update would
+ * make every following one fail too.
+*/
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn get_normalizer(config: &Config) -> Normalizer {
+    Normalizer {
+        transliterate_cyrillic: config.transliterate_cyrillic.unwrap_or(false),
+    }
+}
+
+fn get_bot_username(api: &Api) -> Option<String> {
+    match api.get_me() {
+        Ok(res) => res.result.username,
+        Err(err) => {
+            eprintln!("get_me failed.");
+            dbg!(err);
+            None
+        }
+    }
+}
+
 fn get_config() -> Config {
     let mut config: Config = Config::new();
     let args = Config::parse();
@@ -195,253 +437,428 @@ fn get_config() -> Config {
     if args.lang.is_some() {
         config.lang = args.lang;
     }
+    if args.translations_path.is_some() {
+        config.translations_path = args.translations_path;
+    }
     if args.search_file.is_some() {
         config.search_file = args.search_file;
     }
     if args.reports_path.is_some() {
         config.reports_path = args.reports_path;
     }
-    if config.token.is_none() || config.songs_path.is_none() {
+    if args.fuzzy_cutoff.is_some() {
+        config.fuzzy_cutoff = args.fuzzy_cutoff;
+    }
+    if args.api_url.is_some() {
+        config.api_url = args.api_url;
+    }
+    if args.webhook_url.is_some() {
+        config.webhook_url = args.webhook_url;
+    }
+    if args.webhook_listen.is_some() {
+        config.webhook_listen = args.webhook_listen;
+    }
+    if args.webhook_secret.is_some() {
+        config.webhook_secret = args.webhook_secret;
+    }
+    if args.data_path.is_some() {
+        config.data_path = args.data_path;
+    }
+    if args.workers.is_some() {
+        config.workers = args.workers;
+    }
+    if args.transliterate_cyrillic.is_some() {
+        config.transliterate_cyrillic = args.transliterate_cyrillic;
+    }
+    config.command = args.command;
+    if config.command.is_none() && (config.token.is_none() || config.songs_path.is_none()) {
         eprintln!("Provide at least a --token and a --songs-path.");
         process::exit(-1);
     }
     if config.lang.is_none() {
         config.lang = Some(String::from("en"));
     }
+    if config.api_url.is_none() {
+        config.api_url = Some(String::from(transport::DEFAULT_API_URL));
+    }
+    if config.workers.is_none() {
+        config.workers = Some(4);
+    }
+    if config.workers == Some(0) {
+        eprintln!("--workers has to be at least 1.");
+        process::exit(-1);
+    }
+    if config.webhook_listen.is_none() {
+        config.webhook_listen = Some(String::from("127.0.0.1:8080"));
+    }
+    if let Some(cutoff) = config.fuzzy_cutoff {
+        if !(0.0..=1.0).contains(&cutoff) {
+            eprintln!("--fuzzy-cutoff has to be between 0 and 1.");
+            process::exit(-1);
+        }
+    }
     config
 }
 
+fn run_command(command: Command, config: &Config) {
+    match command {
+        Command::Index { songs_path, output } => {
+            let search_file = chordpro::create_search_file(&songs_path);
+            match output.as_ref().or(config.search_file.as_ref()) {
+                Some(path) => {
+                    if let Err(err) = fs::write(path, search_file) {
+                        eprintln!("Cannot write search file {}.", path);
+                        dbg!(err);
+                        process::exit(-1);
+                    }
+                }
+                None => print!("{}", search_file),
+            }
+        }
+        Command::Check => check(config),
+    }
+}
+
+/*
+ * Exits with 1 if there is any warning,
+ * so that it can be used in scripts.
+*/
+fn check(config: &Config) {
+    let Some(songs_path) = config.songs_path.clone() else {
+        eprintln!("Provide a --songs-path.");
+        process::exit(-1);
+    };
+    // without data_path, so that nothing a running songy uses is written
+    let index = SongIndex::new(
+        &add_ending_slash(songs_path),
+        config.search_file.as_ref(),
+        None,
+        get_normalizer(config),
+    );
+    for warning in &index.warnings {
+        println!("{}", warning);
+    }
+    if !index.warnings.is_empty() {
+        process::exit(1);
+    }
+}
+
 fn add_ending_slash(path: String) -> String {
     if !path.ends_with("/") {
         let mut new_path = path.to_owned();
-        new_path.push_str("/");
-        return new_path;
+        new_path.push('/');
+        new_path
     } else {
-        return path;
+        path
     }
 }
 
-fn handle_text_message(args: &HandleArg) -> Option<HandleResult> {
+fn get_find_song_args(args: &HandleArg) -> FindSongArgs<'_> {
     let mut find_song_args = FindSongArgs {
         search_string: String::new(),
-        songs_path: args.songs_path.clone(),
-        i18n: args.i18n.clone(),
+        index: &args.index,
         search_type: SearchType::Title,
-        search_file: String::new(),
+        fuzzy_cutoff: args.fuzzy_cutoff,
     };
-    if let Some(search_file) = args.search_file.as_ref() {
-        if fs::File::open(search_file).is_ok() {
-            find_song_args.search_type = SearchType::FullText;
-            find_song_args.search_file = search_file.to_string();
-        }
+    if args.index.search_entries.is_some() {
+        find_song_args.search_type = SearchType::FullText;
     }
+    find_song_args
+}
+
+fn handle_text_message(args: &HandleArg) -> Option<HandleResult> {
+    let mut find_song_args = get_find_song_args(args);
     let msg = args.msg.clone().unwrap();
-    let text: &str = msg.text.as_ref().unwrap();
-    let chat_id: u64 = msg.from.as_ref().unwrap().id;
+    let text = strip_bot_username(args, msg.text.as_ref().unwrap())?;
+    let chat_id: i64 = msg.chat.id;
     let mut params = SendMessageParams::builder()
-        .chat_id(ChatId::Integer(chat_id.try_into().unwrap()))
+        .chat_id(ChatId::Integer(chat_id))
         .text("")
         .build();
     match text {
         "/start" => {
             params.text = (args.i18n.start_msg).to_string();
-            send_message(&args.api, &mut params);
+            send_message(args.messenger.as_ref(), &mut params);
         }
         "/list" => {
-            let songs = get_songs(&args.songs_path, None);
-            params.text = form_msg(OutgoingTextMsg::DirEntry(songs));
-            send_message(&args.api, &mut params);
+            pagination::send_song_list(args, chat_id, args.index.songs.clone());
+        }
+        "/language" => {
+            language::send_choice(args, chat_id);
         }
         "/report" => {
             params.text = args.i18n.report.msg.clone();
-            send_message(&args.api, &mut params);
-            return Some(HandleResult {
-                user_id_waiting_for_report: Some(chat_id),
-            });
+            send_message(args.messenger.as_ref(), &mut params);
+            if args.reports_path.is_some() {
+                return Some(HandleResult {
+                    start_dialog: Some(DialogKind::Report),
+                });
+            }
         }
         _ => {
-            if text.starts_with("/") {
-                for name in i18n::get_folder_names(&args.songs_path) {
-                    if text == "/".to_owned() + name.as_str() {
-                        let songs = get_songs(&args.songs_path, Some(&name));
-                        params.text = form_msg(OutgoingTextMsg::DirEntry(songs));
-                        send_message(&args.api, &mut params);
+            if let Some(command) = text.strip_prefix('/') {
+                for name in &args.index.folder_names {
+                    if command == name {
+                        let songs = args.index.songs_in_folder(name);
+                        pagination::send_song_list(args, chat_id, songs);
                         return None;
                     }
                 }
-                let len = text.as_bytes().len();
-                find_song_args.search_string = text[1..len].to_string();
-                match title_search(&find_song_args) {
-                    Ok(files) => {
-                        let file = files.get(0);
-                        let input_file = InputFile::builder().path(file.unwrap().path()).build();
-                        let send_document_params = SendDocumentParams::builder()
-                            .chat_id(ChatId::Integer(chat_id.try_into().unwrap()))
-                            .document(File::InputFile(input_file))
-                            .build();
-                        send_document(&args.api, &send_document_params);
-                    }
-                    Err(err) => {
-                        eprintln!("{}", err.message);
+                if let Some(song) = args.index.get_song_by_slug(command) {
+                    send_song(args, chat_id, song);
+                    return None;
+                }
+                let songs = get_songs_of_command(&args.index, command);
+                match songs.len() {
+                    0 => {
                         params.text = (args.i18n.song_not_found).to_string();
-                        send_message(&args.api, &mut params);
+                        send_message(args.messenger.as_ref(), &mut params);
                     }
+                    1 => send_song(args, chat_id, &songs[0]),
+                    // let the user choose, e.g. between the same song in different folders
+                    _ => pagination::send_song_list(args, chat_id, songs),
                 }
             } else {
                 find_song_args.search_string = text.to_string();
                 match find_song_args.search_type {
                     SearchType::Title => match title_search(&find_song_args) {
-                        Ok(files) => {
-                            params.text = form_msg(OutgoingTextMsg::DirEntry(files));
-                            send_message(&args.api, &mut params);
-                        }
+                        Ok(files) => pagination::send_song_list(args, chat_id, files),
                         Err(err) => {
                             eprintln!("{}", err.message);
                             params.text = (args.i18n.song_not_found).to_string();
-                            send_message(&args.api, &mut params);
+                            send_message(args.messenger.as_ref(), &mut params);
                         }
                     },
                     SearchType::FullText => match full_text_search(&find_song_args) {
                         Ok(search_result) => {
-                            let mut only_one_result: Option<String> = None;
-                            if search_result.ss_in_title.len() == 1
-                                && search_result.ss_in_lyrics.len() == 0
-                            {
-                                only_one_result = Some(search_result.ss_in_title[0].clone());
-                            } else if search_result.ss_in_title.len() == 0
-                                && search_result.ss_in_lyrics.len() == 1
-                            {
-                                only_one_result = Some(search_result.ss_in_lyrics[0].clone());
-                            }
-                            if let Some(new_search_string) = only_one_result {
-                                find_song_args.search_string = new_search_string;
-                                match title_search(&find_song_args) {
-                                    Ok(files) => {
-                                        let file = files.get(0);
-                                        let input_file =
-                                            InputFile::builder().path(file.unwrap().path()).build();
-                                        let send_document_params = SendDocumentParams::builder()
-                                            .chat_id(ChatId::Integer(chat_id.try_into().unwrap()))
-                                            .document(File::InputFile(input_file))
-                                            .build();
-                                        send_document(&args.api, &send_document_params);
-                                    }
-                                    Err(err) => {
-                                        /*
-                                            This can't be reached in theory
-                                            because we've already found a song previously
-                                        */
-                                        eprintln!("{}", err.message);
-                                        params.text = (args.i18n.song_not_found).to_string();
-                                        send_message(&args.api, &mut params);
-                                    }
+                            let mut songs = get_list_of_result(&args.index, &search_result);
+                            match songs.len() {
+                                // the songs of the search file may not exist
+                                0 => {
+                                    params.text = (args.i18n.song_not_found).to_string();
+                                    send_message(args.messenger.as_ref(), &mut params);
                                 }
-                            } else {
-                                let ss_in_title =
-                                    form_msg(OutgoingTextMsg::String(search_result.ss_in_title));
-                                let ss_in_lyrics =
-                                    form_msg(OutgoingTextMsg::String(search_result.ss_in_lyrics));
-                                params.text.push_str(&ss_in_title);
-                                params.text.push_str(&ss_in_lyrics);
-                                send_message(&args.api, &mut params);
+                                1 => send_song(args, chat_id, &songs.remove(0).song),
+                                _ => pagination::send_list(args, chat_id, songs),
                             }
                         }
                         Err(err) => {
                             eprintln!("{}", err.message);
                             params.text = (args.i18n.song_not_found).to_string();
-                            send_message(&args.api, &mut params);
+                            send_message(args.messenger.as_ref(), &mut params);
                         }
                     },
                 }
             }
         }
     }
-    return None;
+    None
+}
+
+/*
+ * In groups commands can be addressed to a bot: /list@songybot.
+ * Returns None for commands addressed to another bot. If get_me
+ * failed, songy can't tell them apart and takes every command.
+*/
+fn strip_bot_username<'a>(args: &HandleArg, text: &'a str) -> Option<&'a str> {
+    if !text.starts_with('/') {
+        return Some(text);
+    }
+    let Some((command, username)) = text.split_once('@') else {
+        return Some(text);
+    };
+    match args.bot_username.as_ref() {
+        Some(bot_username) if !bot_username.eq_ignore_ascii_case(username.trim_end()) => None,
+        _ => Some(command),
+    }
+}
+
+/*
+ * Commands that aren't a slug, e.g. from messages
+ * sent before songy knew slugs, are resolved
+ * by the exact name: /<name> or /<folder>_<name>.
+*/
+fn get_songs_of_command(index: &SongIndex, command: &str) -> Vec<SongFile> {
+    let songs = index.get_songs_by_name(command);
+    if !songs.is_empty() {
+        return songs;
+    }
+    for folder_name in &index.folder_names {
+        if let Some(name) = command.strip_prefix(&format!("{}_", folder_name)) {
+            let folder_songs: Vec<SongFile> = index
+                .songs_in_folder(folder_name)
+                .into_iter()
+                .filter(|song| song.name().to_lowercase() == name.to_lowercase())
+                .collect();
+            if !folder_songs.is_empty() {
+                return folder_songs;
+            }
+        }
+    }
+    vec![]
 }
 
-fn handle_report(args: &HandleArg) -> bool {
+fn handle_report(args: &HandleArg) -> Step {
     let msg = args.msg.clone().unwrap();
     let reports_path = args.reports_path.clone().unwrap();
-    let chat_id: u64 = msg.from.as_ref().unwrap().id;
     let mut params = SendMessageParams::builder()
-        .chat_id(ChatId::Integer(chat_id.try_into().unwrap()))
+        .chat_id(ChatId::Integer(msg.chat.id))
         .text("")
         .build();
     if let Some(voice) = msg.voice {
-        match args.api.get_file(&GetFileParams {
-            file_id: voice.file_id,
-        }) {
-            Ok(file) => {
-                let file_path = file.result.file_path.unwrap();
-                match download_file(&args.token, &file_path) {
-                    Ok(bytes) => save_file(ReportFileType::Voice(bytes), &reports_path),
-                    Err(_) => {}
-                }
+        match args.messenger.download_file(&voice.file_id) {
+            Ok(bytes) => save_file(ReportFileType::Voice(bytes), &reports_path),
+            Err(err) => {
+                eprintln!("download_file failed.");
+                dbg!(err);
             }
-            Err(_) => {}
         }
         params.text = args.i18n.report.success_msg.clone();
-        send_message(&args.api, &mut params);
-        return true;
+        send_message(args.messenger.as_ref(), &mut params);
+        Step::Done
     } else if let Some(text) = msg.text {
-        if text == String::from("/cancel") {
-            params.text = String::from(args.i18n.report.cancel_msg.clone());
-            send_message(&args.api, &mut params);
-            return true;
-        }
         params.text = args.i18n.report.success_msg.clone();
-        send_message(&args.api, &mut params);
+        send_message(args.messenger.as_ref(), &mut params);
         save_file(ReportFileType::Text(text), &reports_path);
-        return true;
+        Step::Done
     } else {
         params.text = args.i18n.report.error_msg.clone();
-        send_message(&args.api, &mut params);
-        return false;
+        send_message(args.messenger.as_ref(), &mut params);
+        Step::Stay
     }
 }
 
-fn download_file(token: &String, file_path: &String) -> Result<Bytes, reqwest::Error> {
-    let url = format!("https://api.telegram.org/file/bot{token}/{file_path}");
-    let bytes = reqwest::blocking::get(url)?.bytes()?;
-    return Ok(bytes);
-}
-
 fn save_file(t: ReportFileType, reports_path: &String) {
-    let timestamp = chrono::offset::Utc::now().timestamp_millis();
-    let filepath = reports_path.to_owned() + "/" + &timestamp.to_string();
     match t {
-        ReportFileType::Voice(bytes) => match fs::File::create(filepath + ".ogg") {
-            Ok(mut file) => {
+        ReportFileType::Voice(bytes) => {
+            if let Some(mut file) = create_report_file(reports_path, ".ogg") {
                 let _res = file.write(&bytes);
             }
-            Err(_) => {}
-        },
-        ReportFileType::Text(mut text) => match fs::File::create(filepath + ".txt") {
-            Ok(mut file) => {
-                text = text + "\n";
+        }
+        ReportFileType::Text(mut text) => {
+            if let Some(mut file) = create_report_file(reports_path, ".txt") {
+                text += "\n";
                 let _res = file.write(text.as_bytes());
             }
-            Err(_) => {}
+        }
+    }
+}
+
+/*
+ * Named by the time of the report, reports of the same
+ * millisecond get a number instead of overwriting each other.
+*/
+fn create_report_file(reports_path: &String, extension: &str) -> Option<fs::File> {
+    let timestamp = chrono::offset::Utc::now().timestamp_millis();
+    let filepath = reports_path.to_owned() + "/" + &timestamp.to_string();
+    for i in 0.. {
+        let mut path = filepath.clone();
+        if i > 0 {
+            path.push_str(&format!("_{}", i));
+        }
+        path.push_str(extension);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => return Some(file),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(err) => {
+                eprintln!("Creating report file {} failed.", path);
+                dbg!(err);
+                return None;
+            }
+        }
+    }
+    None
+}
+
+/*
+ * Searches like a text message but returns the
+ * songs themselves instead of their commands.
+*/
+fn search_songs(args: &HandleArg, search_string: &str) -> Vec<SongFile> {
+    let mut find_song_args = get_find_song_args(args);
+    find_song_args.search_string = search_string.to_string();
+    match find_song_args.search_type {
+        SearchType::Title => title_search(&find_song_args).unwrap_or_default(),
+        SearchType::FullText => match full_text_search(&find_song_args) {
+            Ok(search_result) => get_songs_of_result(&args.index, &search_result),
+            Err(_) => vec![],
         },
     }
 }
 
-fn send_document(api: &Api, params: &SendDocumentParams) {
-    let result = api.send_document(params);
+/*
+ * Songs of the search file that don't
+ * exist in the songs folder are left out.
+*/
+fn get_songs_of_result(index: &SongIndex, search_result: &SearchResult) -> Vec<SongFile> {
+    get_list_of_result(index, search_result)
+        .into_iter()
+        .map(|entry| entry.song)
+        .collect()
+}
+
+fn get_list_of_result(index: &SongIndex, search_result: &SearchResult) -> Vec<ListEntry> {
+    search_result
+        .ss_in_title
+        .iter()
+        .chain(search_result.ss_in_lyrics.iter())
+        .flat_map(|name| {
+            // the same song can be in several folders
+            index
+                .get_songs_by_name(name)
+                .into_iter()
+                .map(|song| ListEntry {
+                    song,
+                    snippet: search_result.snippets.get(name).cloned(),
+                })
+        })
+        .collect()
+}
+
+fn send_song(args: &HandleArg, chat_id: i64, song: &SongFile) {
+    let file_id = lock(&args.file_cache).get(&song.path);
+    if let Some(file_id) = file_id {
+        let send_document_params = SendDocumentParams::builder()
+            .chat_id(ChatId::Integer(chat_id))
+            .document(File::String(file_id))
+            .build();
+        if send_document(args.messenger.as_ref(), &send_document_params).is_some() {
+            return;
+        }
+        // e.g. the file_ids of another bot token don't work, so it's uploaded again
+        lock(&args.file_cache).remove(&song.path);
+    }
+    let input_file = InputFile::builder().path(song.path.clone()).build();
+    let send_document_params = SendDocumentParams::builder()
+        .chat_id(ChatId::Integer(chat_id))
+        .document(File::InputFile(input_file))
+        .build();
+    if let Some(file_id) = send_document(args.messenger.as_ref(), &send_document_params) {
+        lock(&args.file_cache).insert(&song.path, file_id);
+    }
+}
+
+fn send_document(messenger: &dyn Messenger, params: &SendDocumentParams) -> Option<String> {
+    let result = messenger.send_document(params);
     match result {
         Err(err) => {
             eprintln!("send_document failed.");
             dbg!(err);
+            None
         }
-        Ok(_res) => {}
+        Ok(file_id) => file_id,
     }
 }
 
-fn send_message(api: &Api, params: &mut SendMessageParams) {
+fn send_message(messenger: &dyn Messenger, params: &mut SendMessageParams) {
     let text_len = params.text.chars().count();
     let msg_count = text_len as f64 / MAX_TEXT_LEN as f64;
     if msg_count <= 1.0 {
-        let result = api.send_message(params);
+        let result = messenger.send_message(params);
         match result {
             Err(err) => {
                 eprintln!("send_message failed.");
@@ -458,7 +875,7 @@ fn send_message(api: &Api, params: &mut SendMessageParams) {
                     Ok(index) => {
                         part = &text[..index];
                         params.text = part.to_string();
-                        let result = api.send_message(params);
+                        let result = messenger.send_message(params);
                         match result {
                             Err(err) => {
                                 eprintln!("send_message failed.");
@@ -474,7 +891,7 @@ fn send_message(api: &Api, params: &mut SendMessageParams) {
                 }
             } else {
                 params.text = text;
-                let result = api.send_message(params);
+                let result = messenger.send_message(params);
                 match result {
                     Err(err) => {
                         eprintln!("send_message failed.");
@@ -489,7 +906,7 @@ fn send_message(api: &Api, params: &mut SendMessageParams) {
 }
 
 fn find_last_line_break(text: String) -> Result<usize, usize> {
-    let mut i: usize = MAX_TEXT_LEN as usize;
+    let mut i: usize = MAX_TEXT_LEN;
     loop {
         if i == 0 {
             return Err(i);
@@ -508,163 +925,222 @@ fn find_last_line_break(text: String) -> Result<usize, usize> {
     }
 }
 
-fn form_msg(songs: OutgoingTextMsg) -> String {
+/*
+ * One numbered command per line, the numbers match the buttons
+ * of the list. It's html so that the matches in snippets can be bold.
+*/
+fn form_msg(songs: &[ListEntry], first_number: usize) -> String {
     let mut message = String::new();
-    match songs {
-        OutgoingTextMsg::DirEntry(songs) => {
-            for song in songs {
-                let file_name = song.file_name();
-                let filename = file_name.to_str().unwrap();
-                let s: Vec<&str> = filename.split(".").collect();
-                let name = s.get(0).unwrap();
-                let mut command: String = "/".to_string();
-                command.push_str(name);
-                command.push_str("\n");
-                message.push_str(command.as_str());
-            }
+    for (i, entry) in songs.iter().enumerate() {
+        let song = &entry.song;
+        let number = first_number + i;
+        match song.description() {
+            Some(description) => message.push_str(&format!(
+                "{}. /{} — {}\n",
+                number,
+                song.slug,
+                escape_html(&truncate_description(&description))
+            )),
+            None => message.push_str(&format!("{}. /{}\n", number, song.slug)),
         }
-        OutgoingTextMsg::String(songs) => {
-            for song in songs {
-                let mut command = String::from("/");
-                command.push_str(&song);
-                command.push_str("\n");
-                message.push_str(command.as_str());
-            }
+        if let Some(snippet) = entry.snippet.as_ref() {
+            message.push_str(&format!("    {}\n", snippet));
         }
     }
-    return message;
-}
-
-fn title_search(args: &FindSongArgs) -> Result<Vec<DirEntry>, SongNotFound> {
-    let mut exact_match: Option<DirEntry> = None;
-    let mut matches: VecDeque<DirEntry> = VecDeque::new();
-    let mut filename: String;
-    let ss = args.i18n.format(&args.search_string).to_lowercase();
-
-    for file in get_songs(&args.songs_path, None) {
-        filename = file.file_name().to_str().unwrap().to_string();
-        let name = filename
-            .split(".")
-            .collect::<Vec<&str>>()
-            .get(0)
-            .unwrap()
-            .to_string()
-            .to_lowercase();
-        if name == ss {
-            exact_match = Some(file);
-        } else if name.starts_with(&ss) {
-            matches.push_front(file);
-        } else if name.contains(&ss) {
-            matches.push_back(file);
-        }
+    message
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn truncate_description(description: &str) -> String {
+    if description.chars().count() <= MAX_DESCRIPTION_LEN {
+        return description.to_string();
     }
-    let mut result: Vec<DirEntry> = Vec::new();
-    if let Some(entry) = exact_match {
-        result.push(entry);
+    let truncated: String = description.chars().take(MAX_DESCRIPTION_LEN - 1).collect();
+    format!("{}…", truncated.trim_end())
+}
+
+fn title_search(args: &FindSongArgs) -> Result<Vec<SongFile>, SongNotFound> {
+    let mut exact_matches: Vec<SongFile> = vec![];
+    let mut matches: VecDeque<SongFile> = VecDeque::new();
+    let mut term_matches: Vec<SongFile> = vec![];
+    let mut fuzzy_matches: Vec<(f64, SongFile)> = vec![];
+    let normalizer = args.index.normalizer;
+    let ss = normalizer.for_title_search(&remove_quotes(&args.search_string));
+    let terms: Vec<String> = get_search_terms(&args.search_string)
+        .iter()
+        .map(|term| normalizer.for_title_search(term))
+        .filter(|term| !term.is_empty())
+        .collect();
+    if ss.is_empty() {
+        return Err(SongNotFound {
+            message: String::from("Didn't find any song."),
+        });
+    }
+
+    for file in &args.index.songs {
+        // the command is latin even if the name isn't, so both are searched
+        let names = [
+            normalizer.for_title_search(file.name()),
+            normalizer.for_title_search(&file.slug),
+        ];
+        if names.contains(&ss) {
+            exact_matches.push(file.clone());
+        } else if names.iter().any(|name| name.starts_with(&ss)) {
+            matches.push_front(file.clone());
+        } else if names.iter().any(|name| name.contains(&ss)) {
+            matches.push_back(file.clone());
+        } else if names.iter().any(|name| contains_all(&terms, name)) {
+            term_matches.push(file.clone());
+        } else if let Some(cutoff) = args.fuzzy_cutoff {
+            let score = names
+                .iter()
+                .map(|name| fuzzy::similarity_of_all(&terms, name))
+                .fold(0.0, f64::max);
+            if score >= cutoff {
+                fuzzy_matches.push((score, file.clone()));
+            }
+        }
     }
+    let mut result: Vec<SongFile> = exact_matches;
     result.append(&mut matches.into_iter().collect());
-    if result.len() > 0 {
-        return Ok(result);
+    result.append(&mut term_matches);
+    result.append(&mut sort_by_score(fuzzy_matches));
+    if !result.is_empty() {
+        Ok(result)
     } else {
-        return Err(SongNotFound {
+        Err(SongNotFound {
             message: String::from("Didn't find any song."),
-        });
+        })
     }
 }
 
 fn full_text_search(args: &FindSongArgs) -> Result<SearchResult, SongNotFound> {
     let mut ss_in_title: Vec<String> = vec![];
     let mut ss_in_lyrics: Vec<String> = vec![];
-    let ss = prepare_for_fulltext_search(&args.search_string);
-    let content = fs::read_to_string(&args.search_file).unwrap();
-    for line in content.lines() {
-        let s_line: Vec<&str> = line.split(':').collect();
-        let name = s_line.get(0).unwrap();
-        let song_title = s_line.get(1).unwrap();
-        let song_lyrics = s_line.get(2).unwrap();
-        if song_title.starts_with(&ss) {
+    let mut terms_in_title: Vec<String> = vec![];
+    let mut terms_in_lyrics: Vec<String> = vec![];
+    let mut fuzzy_in_title: Vec<(f64, String)> = vec![];
+    let mut fuzzy_in_lyrics: Vec<(f64, String)> = vec![];
+    let mut snippets: HashMap<String, String> = HashMap::new();
+    let normalizer = args.index.normalizer;
+    let ss = normalizer.for_fulltext_search(&args.search_string);
+    let terms: Vec<String> = get_search_terms(&args.search_string)
+        .iter()
+        .map(|term| normalizer.for_fulltext_search(term))
+        .filter(|term| !term.is_empty())
+        .collect();
+    let hymnal_number = args.search_string.trim();
+    for entry in args.index.search_entries.as_ref().unwrap() {
+        let name = &entry.name;
+        let song_lyrics = &entry.lyrics;
+        if entry.hymnal_number.as_deref() == Some(hymnal_number)
+            || (!ss.is_empty() && entry.titles().any(|title| title.starts_with(&ss)))
+        {
             // move found song to the beginning
             let mut temp = vec![name.to_string()];
             temp.append(&mut ss_in_title);
             ss_in_title = temp;
-        } else if song_title.contains(&ss) {
+        } else if ss.is_empty() {
+            continue;
+        } else if entry.titles().any(|title| title.contains(&ss)) {
             ss_in_title.push(name.to_string());
+        } else if entry.titles().any(|title| contains_all(&terms, title)) {
+            terms_in_title.push(name.to_string());
         } else if song_lyrics.contains(&ss) {
             ss_in_lyrics.push(name.to_string());
+            if let Some(snippet) =
+                snippet::get_snippet(entry, normalizer, std::slice::from_ref(&ss))
+            {
+                snippets.insert(name.to_string(), snippet);
+            }
+        } else if contains_all(&terms, song_lyrics) {
+            terms_in_lyrics.push(name.to_string());
+            if let Some(snippet) = snippet::get_snippet(entry, normalizer, &terms) {
+                snippets.insert(name.to_string(), snippet);
+            }
+        } else if let Some(cutoff) = args.fuzzy_cutoff {
+            let title_score = entry
+                .titles()
+                .map(|title| fuzzy::similarity_of_all(&terms, title))
+                .fold(0.0, f64::max);
+            if title_score >= cutoff {
+                fuzzy_in_title.push((title_score, name.to_string()));
+                continue;
+            }
+            let lyrics_score = fuzzy::similarity_of_all(&terms, song_lyrics);
+            if lyrics_score >= cutoff {
+                fuzzy_in_lyrics.push((lyrics_score, name.to_string()));
+            }
         }
     }
-    if ss_in_title.len() == 0 && ss_in_lyrics.len() == 0 {
-        return Err(SongNotFound {
+    ss_in_title.append(&mut terms_in_title);
+    ss_in_title.append(&mut sort_by_score(fuzzy_in_title));
+    ss_in_lyrics.append(&mut terms_in_lyrics);
+    ss_in_lyrics.append(&mut sort_by_score(fuzzy_in_lyrics));
+    if ss_in_title.is_empty() && ss_in_lyrics.is_empty() {
+        Err(SongNotFound {
             message: String::from("Didn't find any song."),
-        });
+        })
     } else {
-        return Ok(SearchResult {
+        Ok(SearchResult {
             ss_in_title,
             ss_in_lyrics,
-        });
+            snippets,
+        })
     }
 }
 
-fn prepare_for_fulltext_search(string: &String) -> String {
-    let mut res = String::new();
-    // let mut is_last_line_break = false;
-    for c in string.chars() {
-        if c.is_alphabetic() {
-            res.push(c);
-            // is_last_line_break = false;
-        }
-        /* if c == '\n' || c == ' ' {
-            if !is_last_line_break {
-                res.push(' ');
-                is_last_line_break = true;
+/*
+ * Every word of the search string is a term on its own,
+ * except for words in quotes which are kept together
+ * as one term (phrase search).
+ * e.g. 'tender "never let me"' -> ["tender", "never let me"]
+*/
+fn get_search_terms(search_string: &str) -> Vec<String> {
+    let mut terms: Vec<String> = vec![];
+    for (i, part) in search_string.split(is_quote).enumerate() {
+        if i % 2 == 1 {
+            if !part.trim().is_empty() {
+                terms.push(part.trim().to_string());
+            }
+        } else {
+            for word in part.split_whitespace() {
+                terms.push(word.to_string());
             }
-        } */
+        }
     }
-    res = res.to_lowercase();
-    return res;
+    terms
 }
 
-fn get_songs(songs_path: &String, folder_name: Option<&String>) -> Vec<DirEntry> {
-    match folder_name {
-        Some(name) => {
-            return get_files_recursive(&(songs_path.to_owned() + name));
-        }
-        None => {
-            return get_files_recursive(songs_path);
-        }
-    }
+/*
+ * Phones like to replace " with typographic quotes.
+*/
+fn is_quote(c: char) -> bool {
+    matches!(c, '"' | '“' | '”' | '„')
 }
 
-fn get_files_recursive(folder_path: &String) -> Vec<DirEntry> {
-    let path = fs::read_dir(folder_path);
-    let mut is_dir: bool;
-    let mut songs: Vec<DirEntry> = vec![];
-    match path {
-        Ok(read_dir) => {
-            for r in read_dir {
-                match r {
-                    Ok(dir_entry) => {
-                        is_dir = dir_entry.file_type().unwrap().is_dir();
-                        if is_dir {
-                            let path = dir_entry.path().to_str().unwrap().to_string();
-                            for song in get_files_recursive(&path) {
-                                songs.push(song);
-                            }
-                        } else {
-                            songs.push(dir_entry);
-                        }
-                    }
-                    Err(err) => {
-                        eprintln!("Cannot access filepath.");
-                        dbg!(err);
-                    }
-                }
-            }
-        }
-        Err(_) => {
-            eprintln!("Cannot open/read or what ever the path {}.", folder_path);
-        }
-    }
-    songs.sort_by_key(|name| name.file_name().into_string().unwrap().to_lowercase());
-    return songs;
+fn remove_quotes(search_string: &str) -> String {
+    search_string.replace(is_quote, "")
+}
+
+/*
+ * A single term is already covered
+ * by the plain substring search.
+*/
+fn contains_all(terms: &[String], text: &str) -> bool {
+    terms.len() > 1 && terms.iter().all(|term| text.contains(term.as_str()))
+}
+
+/*
+ * Best matches first, matches with the
+ * same score keep their alphabetical order.
+*/
+fn sort_by_score<T>(mut matches: Vec<(f64, T)>) -> Vec<T> {
+    matches.sort_by(|a, b| b.0.total_cmp(&a.0));
+    matches.into_iter().map(|(_, m)| m).collect()
 }
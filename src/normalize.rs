@@ -0,0 +1,166 @@
+use icu_normalizer::DecomposingNormalizer;
+
+/*
+ * Brings file names, titles, lyrics and search strings
+ * into the same form so that they can be compared
+ * regardless of case, accents and the language:
+ * "Été" and "ete" or "Größe" and "grosse" are the same.
+*/
+#[derive(Clone, Copy, Default)]
+pub struct Normalizer {
+    // so that latin search strings find cyrillic songs and the other way around
+    pub transliterate_cyrillic: bool,
+}
+
+impl Normalizer {
+    pub fn normalize(&self, text: &str) -> String {
+        let mut text = text.to_lowercase();
+        if self.transliterate_cyrillic {
+            text = text
+                .chars()
+                .map(|c| transliterate(c).map_or(c.to_string(), String::from))
+                .collect();
+        }
+        remove_accents(&text)
+    }
+
+    /*
+     * For comparing with file names: words are
+     * separated by a single space, no matter if
+     * it was an underscore, a dash or spaces before.
+     */
+    pub fn for_title_search(&self, text: &str) -> String {
+        let normalized = self.normalize(text);
+        normalized
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    // only letters, so that line breaks and punctuation in lyrics don't matter
+    pub fn for_fulltext_search(&self, text: &str) -> String {
+        self.normalize(text)
+            .chars()
+            .filter(|c| c.is_alphabetic())
+            .collect()
+    }
+}
+
+/*
+ * A cyrillic letter written with latin letters, still
+ * uppercase if it was. Slugs are always transliterated
+ * because commands can only consist of latin letters.
+*/
+pub fn to_latin(c: char) -> String {
+    match transliterate(to_lower(c)) {
+        Some(latin) => in_case_of(c, latin),
+        None => c.to_string(),
+    }
+}
+
+/*
+ * The letters without their accents, in the case they
+ * were: "Été" becomes "Ete" and "Øre" becomes "Ore".
+*/
+pub fn remove_accents(text: &str) -> String {
+    // splits letters into the base letter and its accents (NFKD)
+    let text = DecomposingNormalizer::new_nfkd().normalize(text);
+    text.chars()
+        .filter(|c| !is_combining_mark(*c))
+        .map(|c| match expand(to_lower(c)) {
+            Some(expanded) => in_case_of(c, expanded),
+            None => c.to_string(),
+        })
+        .collect()
+}
+
+fn to_lower(c: char) -> char {
+    c.to_lowercase().next().unwrap()
+}
+
+// the lowercase replacement of c, capitalized if c is uppercase
+fn in_case_of(c: char, replacement: &str) -> String {
+    if c == to_lower(c) {
+        return replacement.to_string();
+    }
+    let mut chars = replacement.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/*
+ * Accents, umlaut dots and the like, which
+ * NFKD splits off of the letters.
+*/
+fn is_combining_mark(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE20}'..='\u{FE2F}')
+}
+
+/*
+ * Lowercase letters that NFKD doesn't split.
+*/
+fn expand(c: char) -> Option<&'static str> {
+    let expanded = match c {
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'ø' => "o",
+        'đ' => "d",
+        'ł' => "l",
+        'þ' => "th",
+        _ => return None,
+    };
+    Some(expanded)
+}
+
+/*
+ * Russian, ukrainian and moldovan cyrillic letters
+ * as they are commonly written with latin letters.
+ * Runs before NFKD, which would make й an и.
+*/
+fn transliterate(c: char) -> Option<&'static str> {
+    let latin = match c {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' | 'ґ' => "g",
+        'д' => "d",
+        'е' | 'ё' | 'э' => "e",
+        'є' => "ye",
+        'ж' => "zh",
+        'ӂ' => "j",
+        'з' => "z",
+        'и' | 'і' => "i",
+        'ї' => "yi",
+        'й' | 'ы' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "h",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ю' => "yu",
+        'я' => "ya",
+        _ => return None,
+    };
+    Some(latin)
+}
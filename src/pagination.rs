@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use frankenstein::api_params::{
+    AnswerCallbackQueryParams, EditMessageTextParams, ReplyMarkup, SendMessageParams,
+};
+use frankenstein::objects::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup};
+use frankenstein::{ChatId, ParseMode};
+
+use crate::song_index::SongFile;
+use crate::HandleArg;
+
+const SONGS_PER_PAGE: usize = 20;
+const BUTTONS_PER_ROW: usize = 5;
+/*
+ * The buttons of a list only work as long as the
+ * list is kept, older lists are dropped first.
+*/
+const MAX_LISTS: usize = 1000;
+
+/*
+ * A song of a list, with the part of the
+ * lyrics it was found by, if it was.
+*/
+#[derive(Clone)]
+pub struct ListEntry {
+    pub song: SongFile,
+    pub snippet: Option<String>,
+}
+
+impl From<SongFile> for ListEntry {
+    fn from(song: SongFile) -> Self {
+        Self {
+            song,
+            snippet: None,
+        }
+    }
+}
+
+/*
+ * The song lists that have been sent with buttons.
+ * The callback data of a button only contains the
+ * id of its list because it's limited to 64 bytes.
+*/
+pub struct SongLists {
+    // shared, so that turning a page doesn't copy the list
+    lists: VecDeque<(u64, Arc<[ListEntry]>)>,
+    next_id: u64,
+}
+
+impl SongLists {
+    pub fn new() -> Self {
+        Self {
+            lists: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    fn add(&mut self, songs: Arc<[ListEntry]>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        if self.lists.len() >= MAX_LISTS {
+            self.lists.pop_front();
+        }
+        self.lists.push_back((id, songs));
+        id
+    }
+
+    fn get(&self, id: u64) -> Option<Arc<[ListEntry]>> {
+        self.lists
+            .iter()
+            .find(|(list_id, _)| *list_id == id)
+            .map(|(_, songs)| songs.clone())
+    }
+}
+
+enum Callback {
+    Page(u64, usize),
+    Song(u64, usize),
+}
+
+impl Callback {
+    fn parse(data: &str) -> Option<Self> {
+        let s_data: Vec<&str> = data.split(':').collect();
+        if s_data.len() != 3 {
+            return None;
+        }
+        let id: u64 = s_data[1].parse().ok()?;
+        let i: usize = s_data[2].parse().ok()?;
+        match s_data[0] {
+            "page" => Some(Callback::Page(id, i)),
+            "song" => Some(Callback::Song(id, i)),
+            _ => None,
+        }
+    }
+
+    fn to_data(&self) -> String {
+        match self {
+            Callback::Page(id, page) => format!("page:{}:{}", id, page),
+            Callback::Song(id, i) => format!("song:{}:{}", id, i),
+        }
+    }
+}
+
+/*
+ * Sends the first page of the list, the
+ * other pages are shown by editing the message.
+*/
+pub fn send_song_list(args: &HandleArg, chat_id: i64, songs: Vec<SongFile>) {
+    send_list(
+        args,
+        chat_id,
+        songs.into_iter().map(ListEntry::from).collect(),
+    );
+}
+
+pub fn send_list(args: &HandleArg, chat_id: i64, songs: Vec<ListEntry>) {
+    if songs.is_empty() {
+        return;
+    }
+    let songs: Arc<[ListEntry]> = songs.into();
+    let id = crate::lock(&args.song_lists).add(songs.clone());
+    let (text, keyboard) = get_page(args, id, &songs, 0);
+    let mut params = SendMessageParams::builder()
+        .chat_id(ChatId::Integer(chat_id))
+        .text(text)
+        .parse_mode(ParseMode::Html)
+        .reply_markup(ReplyMarkup::InlineKeyboardMarkup(keyboard))
+        .build();
+    crate::send_message(args.messenger.as_ref(), &mut params);
+}
+
+pub fn handle_callback_query(args: &HandleArg, query: &CallbackQuery) {
+    let mut answer_params = AnswerCallbackQueryParams::builder()
+        .callback_query_id(query.id.clone())
+        .build();
+    let callback = query.data.as_deref().and_then(Callback::parse);
+    let songs = match &callback {
+        Some(Callback::Page(id, _)) | Some(Callback::Song(id, _)) => {
+            crate::lock(&args.song_lists).get(*id)
+        }
+        None => None,
+    };
+    if songs.is_none() {
+        answer_params.text = Some(args.i18n.list_expired.clone());
+    }
+    if let Err(err) = args.messenger.answer_callback_query(&answer_params) {
+        eprintln!("answer_callback_query failed.");
+        dbg!(err);
+    }
+    let (Some(callback), Some(songs)) = (callback, songs) else {
+        return;
+    };
+    let chat_id: i64 = match query.message.as_ref() {
+        Some(msg) => msg.chat.id,
+        None => query.from.id.try_into().unwrap(),
+    };
+    match callback {
+        Callback::Page(id, page) => {
+            let Some(msg) = query.message.as_ref() else {
+                return;
+            };
+            let (text, keyboard) = get_page(args, id, &songs, page);
+            let params = EditMessageTextParams::builder()
+                .chat_id(ChatId::Integer(chat_id))
+                .message_id(msg.message_id)
+                .text(text)
+                .parse_mode(ParseMode::Html)
+                .reply_markup(keyboard)
+                .build();
+            if let Err(err) = args.messenger.edit_message_text(&params) {
+                eprintln!("edit_message_text failed.");
+                dbg!(err);
+            }
+        }
+        Callback::Song(_, i) => {
+            if let Some(entry) = songs.get(i) {
+                crate::send_song(args, chat_id, &entry.song);
+            }
+        }
+    }
+}
+
+fn get_page(
+    args: &HandleArg,
+    id: u64,
+    songs: &[ListEntry],
+    page: usize,
+) -> (String, InlineKeyboardMarkup) {
+    let pages = songs.len().div_ceil(SONGS_PER_PAGE);
+    let page = page.min(pages - 1);
+    let start = page * SONGS_PER_PAGE;
+    let end = songs.len().min(start + SONGS_PER_PAGE);
+    let mut text = String::new();
+    if pages > 1 {
+        let header = format!("{} {}/{}\n", args.i18n.page, page + 1, pages);
+        text.push_str(&crate::escape_html(&header));
+    }
+    text.push_str(&crate::form_msg(&songs[start..end], start + 1));
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+    for row_start in (start..end).step_by(BUTTONS_PER_ROW) {
+        let row_end = end.min(row_start + BUTTONS_PER_ROW);
+        keyboard.push(
+            (row_start..row_end)
+                .map(|i| get_button(&(i + 1).to_string(), Callback::Song(id, i)))
+                .collect(),
+        );
+    }
+    let mut navigation: Vec<InlineKeyboardButton> = vec![];
+    if page > 0 {
+        navigation.push(get_button("«", Callback::Page(id, page - 1)));
+    }
+    if page + 1 < pages {
+        navigation.push(get_button("»", Callback::Page(id, page + 1)));
+    }
+    if !navigation.is_empty() {
+        keyboard.push(navigation);
+    }
+    (
+        text,
+        InlineKeyboardMarkup::builder()
+            .inline_keyboard(keyboard)
+            .build(),
+    )
+}
+
+fn get_button(text: &str, callback: Callback) -> InlineKeyboardButton {
+    InlineKeyboardButton::builder()
+        .text(text)
+        .callback_data(callback.to_data())
+        .build()
+}
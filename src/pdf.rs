@@ -0,0 +1,400 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub fn is_pdf_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+// the trailer and in most files also the info dictionary are in it
+const TAIL_LEN: u64 = 64 * 1024;
+// more than any info dictionary needs
+const OBJECT_LEN: u64 = 16 * 1024;
+// each incremental update of a file adds one
+const MAX_XREF_SECTIONS: usize = 32;
+/*
+ * Writers usually put one subsection per section, updates one per
+ * changed range of objects. Damaged files shouldn't be read forever.
+*/
+const MAX_XREF_SUBSECTIONS: usize = 1024;
+// "0000000017 00000 n\r\n"
+const XREF_ENTRY_LEN: u64 = 20;
+
+/*
+ * The title and author of the document information
+ * dictionary the trailer points to with /Info.
+ * Only uncompressed dictionaries can be read,
+ * which is how most programs write them.
+ * Only the end of the file and the dictionary
+ * itself are read, not the whole file.
+*/
+pub fn read_info(path: &Path) -> (Option<String>, Option<String>) {
+    let Some(info) = File::open(path)
+        .ok()
+        .and_then(|mut file| read_info_object(&mut file))
+    else {
+        return (None, None);
+    };
+    (get_string(&info, b"/Title"), get_string(&info, b"/Author"))
+}
+
+/*
+ * The content of "<n> <generation> obj ... endobj", looked
+ * for in the tail first and then where the cross-reference
+ * table says it is. Cross-reference streams are compressed
+ * and can't be read, but the programs writing them
+ * usually put the dictionary at the end.
+*/
+fn read_info_object(file: &mut File) -> Option<Vec<u8>> {
+    let len = file.metadata().ok()?.len();
+    let tail = read_at(file, len.saturating_sub(TAIL_LEN), TAIL_LEN)?;
+    let start = rfind(&tail, b"/Info")? + b"/Info".len();
+    let reference = String::from_utf8_lossy(&tail[start..tail.len().min(start + 32)]);
+    let mut parts = reference.split_whitespace();
+    let number: u64 = parts.next()?.parse().ok()?;
+    let generation: u64 = parts.next()?.parse().ok()?;
+    let header = format!("{} {} obj", number, generation);
+    if let Some(object) = get_object(&tail, &header) {
+        return Some(object.to_vec());
+    }
+    let offset = get_object_offset(file, &tail, number)?;
+    let bytes = read_at(file, offset, OBJECT_LEN)?;
+    get_object(&bytes, &header).map(<[u8]>::to_vec)
+}
+
+// the last object with this header, updates are appended
+fn get_object<'a>(bytes: &'a [u8], header: &str) -> Option<&'a [u8]> {
+    let mut object = None;
+    let mut from = 0;
+    while let Some(i) = find(&bytes[from..], header.as_bytes()) {
+        let start = from + i;
+        // 1 0 obj must not be found in 11 0 obj
+        if start == 0 || bytes[start - 1].is_ascii_whitespace() {
+            let content = &bytes[start + header.len()..];
+            let end = find(content, b"endobj").unwrap_or(content.len());
+            object = Some(&content[..end]);
+        }
+        from = start + header.len();
+    }
+    object
+}
+
+/*
+ * Goes through the cross-reference sections from the last
+ * one back to the first, every update links its previous one.
+*/
+fn get_object_offset(file: &mut File, tail: &[u8], number: u64) -> Option<u64> {
+    let start = rfind(tail, b"startxref")? + b"startxref".len();
+    let mut section = read_number(&tail[start..])?;
+    for _ in 0..MAX_XREF_SECTIONS {
+        match read_xref_section(file, section, number)? {
+            XrefLookup::Found(offset) => return Some(offset),
+            XrefLookup::Previous(previous) => section = previous,
+        }
+    }
+    None
+}
+
+enum XrefLookup {
+    Found(u64),
+    // the offset of the previous section
+    Previous(u64),
+}
+
+/*
+ * A section is "xref", subsections of "<first number> <count>"
+ * with an entry of 20 bytes for every object, and the trailer.
+ * The numbers come from the file, a damaged one mustn't
+ * make them overflow.
+*/
+fn read_xref_section(file: &mut File, start: u64, number: u64) -> Option<XrefLookup> {
+    if !read_at(file, start, 4)?.starts_with(b"xref") {
+        return None;
+    }
+    let mut pos = start.checked_add(4)?;
+    for _ in 0..MAX_XREF_SUBSECTIONS {
+        let bytes = read_at(file, pos, 64)?;
+        let skipped = bytes.iter().position(|b| !b.is_ascii_whitespace())?;
+        pos = pos.checked_add(skipped as u64)?;
+        let bytes = &bytes[skipped..];
+        if bytes.starts_with(b"trailer") {
+            let trailer = read_at(file, pos, 1024)?;
+            let end = find(&trailer, b">>").unwrap_or(trailer.len());
+            let previous = find(&trailer[..end], b"/Prev")?;
+            return read_number(&trailer[previous + b"/Prev".len()..]).map(XrefLookup::Previous);
+        }
+        let line_len = bytes.iter().position(|b| *b == b'\r' || *b == b'\n')?;
+        let line = String::from_utf8_lossy(&bytes[..line_len]);
+        let mut parts = line.split_whitespace();
+        let first: u64 = parts.next()?.parse().ok()?;
+        let count: u64 = parts.next()?.parse().ok()?;
+        let line_end = bytes[line_len..]
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(bytes.len() - line_len);
+        let entries = pos.checked_add((line_len + line_end) as u64)?;
+        if (first..first.checked_add(count)?).contains(&number) {
+            let entry_offset = (number - first)
+                .checked_mul(XREF_ENTRY_LEN)
+                .and_then(|len| entries.checked_add(len))?;
+            let entry = read_at(file, entry_offset, XREF_ENTRY_LEN)?;
+            let entry = String::from_utf8_lossy(&entry);
+            let mut parts = entry.split_whitespace();
+            let offset: u64 = parts.next()?.parse().ok()?;
+            // "f" is a free entry of a deleted object
+            return match parts.nth(1)? {
+                "n" => Some(XrefLookup::Found(offset)),
+                _ => None,
+            };
+        }
+        pos = count
+            .checked_mul(XREF_ENTRY_LEN)
+            .and_then(|len| entries.checked_add(len))?;
+    }
+    None
+}
+
+fn read_at(file: &mut File, offset: u64, len: u64) -> Option<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut bytes = vec![];
+    file.take(len).read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+// the number after the whitespace at the start
+fn read_number(bytes: &[u8]) -> Option<u64> {
+    let digits: String = bytes
+        .iter()
+        .skip_while(|b| b.is_ascii_whitespace())
+        .take_while(|b| b.is_ascii_digit())
+        .map(|b| *b as char)
+        .collect();
+    digits.parse().ok()
+}
+
+/*
+ * A string value of the dictionary, either
+ * written as (literal) or as <hex> string.
+*/
+fn get_string(dictionary: &[u8], key: &[u8]) -> Option<String> {
+    let start = find(dictionary, key)? + key.len();
+    let value = &dictionary[start..];
+    let value = &value[value.iter().position(|b| !b.is_ascii_whitespace())?..];
+    let bytes = match value.first()? {
+        b'(' => read_literal(&value[1..]),
+        b'<' => read_hex(&value[1..]),
+        _ => return None,
+    };
+    let text = decode(&bytes);
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    Some(text.to_string())
+}
+
+fn read_literal(value: &[u8]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = vec![];
+    let mut depth = 0;
+    let mut i = 0;
+    while i < value.len() {
+        let b = value[i];
+        i += 1;
+        match b {
+            b'\\' => {
+                let Some(&escaped) = value.get(i) else {
+                    break;
+                };
+                i += 1;
+                match escaped {
+                    b'n' => bytes.push(b'\n'),
+                    b'r' => bytes.push(b'\r'),
+                    b't' => bytes.push(b'\t'),
+                    b'b' => bytes.push(8),
+                    b'f' => bytes.push(12),
+                    b'0'..=b'7' => {
+                        // up to three octal digits
+                        let mut code = u32::from(escaped - b'0');
+                        for _ in 0..2 {
+                            match value.get(i) {
+                                Some(digit @ b'0'..=b'7') => {
+                                    code = code * 8 + u32::from(digit - b'0');
+                                    i += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        bytes.push(code as u8);
+                    }
+                    // a backslash at the end of a line continues the string
+                    b'\r' | b'\n' => {
+                        if escaped == b'\r' && value.get(i) == Some(&b'\n') {
+                            i += 1;
+                        }
+                    }
+                    _ => bytes.push(escaped),
+                }
+            }
+            b'(' => {
+                depth += 1;
+                bytes.push(b);
+            }
+            b')' if depth == 0 => break,
+            b')' => {
+                depth -= 1;
+                bytes.push(b);
+            }
+            _ => bytes.push(b),
+        }
+    }
+    bytes
+}
+
+fn read_hex(value: &[u8]) -> Vec<u8> {
+    let mut digits: Vec<u8> = value
+        .iter()
+        .take_while(|b| **b != b'>')
+        .filter_map(|b| (*b as char).to_digit(16))
+        .map(|digit| digit as u8)
+        .collect();
+    // a missing last digit is 0
+    if digits.len() % 2 == 1 {
+        digits.push(0);
+    }
+    digits
+        .chunks(2)
+        .map(|pair| pair[0] * 16 + pair[1])
+        .collect()
+}
+
+/*
+ * Text strings are UTF-16 with a byte order mark,
+ * UTF-8 with one (since pdf 2.0) or PDFDocEncoding,
+ * which is close enough to latin-1 for titles.
+*/
+fn decode(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+    if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(utf8).to_string();
+    }
+    bytes.iter().map(|b| *b as char).collect()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::{env, fs, process};
+
+    // so that the info dictionary at the start isn't in the tail
+    const FILLER_LEN: usize = 100 * 1024;
+
+    /*
+     * A pdf with the objects at the start, a long comment
+     * and a cross-reference table of the objects at the end.
+     * The second xref section is an update that only changes the catalog.
+     */
+    fn write_pdf(name: &str, info: &str, is_updated: bool) -> PathBuf {
+        let mut pdf = String::from("%PDF-1.4\n");
+        let objects = [info, "<< /Type /Catalog >>"];
+        let mut offsets = vec![];
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+        }
+        pdf.push_str(&format!("%{}\n", "x".repeat(FILLER_LEN)));
+        let xref = pdf.len();
+        pdf.push_str("xref\n0 3\n0000000000 65535 f\r\n");
+        for offset in offsets {
+            pdf.push_str(&format!("{:010} 00000 n\r\n", offset));
+        }
+        pdf.push_str("trailer\n<< /Size 3 /Root 2 0 R /Info 1 0 R >>\n");
+        pdf.push_str(&format!("startxref\n{}\n%%EOF\n", xref));
+        if is_updated {
+            let catalog = pdf.len();
+            pdf.push_str("2 0 obj\n<< /Type /Catalog /Lang (de) >>\nendobj\n");
+            let update = pdf.len();
+            pdf.push_str(&format!("xref\n2 1\n{:010} 00000 n\r\n", catalog));
+            pdf.push_str(&format!(
+                "trailer\n<< /Size 3 /Root 2 0 R /Info 1 0 R /Prev {}>>\n",
+                xref
+            ));
+            pdf.push_str(&format!("startxref\n{}\n%%EOF\n", update));
+        }
+        let path = env::temp_dir().join(format!("songy-test-{}-{}.pdf", process::id(), name));
+        fs::write(&path, pdf).unwrap();
+        path
+    }
+
+    #[test]
+    fn info_is_found_through_the_xref_table() {
+        let path = write_pdf(
+            "xref",
+            "<< /Title (Love Me Tender) /Author <FEFF0045006C007600690073> >>",
+            false,
+        );
+        assert_eq!(
+            super::read_info(&path),
+            (
+                Some(String::from("Love Me Tender")),
+                Some(String::from("Elvis"))
+            )
+        );
+    }
+
+    #[test]
+    fn info_is_found_through_earlier_updates() {
+        let path = write_pdf("update", "<< /Title (Stille Nacht) >>", true);
+        assert_eq!(
+            super::read_info(&path),
+            (Some(String::from("Stille Nacht")), None)
+        );
+    }
+
+    #[test]
+    fn damaged_xref_tables_are_given_up_on() {
+        for (i, subsection) in ["18446744073709551615 2", "2 1000000000000000000"]
+            .iter()
+            .enumerate()
+        {
+            let mut pdf = format!(
+                "%PDF-1.4\n1 0 obj\n<< /Title (Song) >>\nendobj\n%{}\n",
+                "x".repeat(FILLER_LEN)
+            );
+            let xref = pdf.len();
+            pdf.push_str(&format!("xref\n{}\n0000000009 00000 n\r\n", subsection));
+            pdf.push_str("trailer\n<< /Size 2 /Info 1 0 R >>\n");
+            pdf.push_str(&format!("startxref\n{}\n%%EOF\n", xref));
+            let path =
+                env::temp_dir().join(format!("songy-test-{}-damaged-{}.pdf", process::id(), i));
+            fs::write(&path, pdf).unwrap();
+            assert_eq!(super::read_info(&path), (None, None));
+        }
+    }
+
+    #[test]
+    fn files_without_info_have_no_title() {
+        let path = env::temp_dir().join(format!("songy-test-{}-empty.pdf", process::id()));
+        fs::write(&path, "").unwrap();
+        assert_eq!(super::read_info(&path), (None, None));
+    }
+}
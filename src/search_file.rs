@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+
+/*
+ * Version 2 search files start with this line,
+ * every following line is one song as json object.
+ * Files without it are read in the legacy format:
+ * <filename_without_extension>:<song_title>:<song_lyrics>
+*/
+const HEADER: &str = r#"{"version":2}"#;
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct SongRecord {
+    pub name: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternative_titles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub composer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tempo: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hymnal_number: Option<String>,
+    #[serde(default)]
+    pub lyrics: String,
+    // titles of legacy files are often shrunk like the lyrics
+    #[serde(skip)]
+    pub is_legacy: bool,
+}
+
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+/*
+ * Malformed lines are skipped and
+ * returned as errors with their line number.
+*/
+pub fn parse(content: &str) -> (Vec<SongRecord>, Vec<ParseError>) {
+    let mut lines = content.lines().enumerate().peekable();
+    let mut version = 1;
+    if let Some((_, first_line)) = lines.peek() {
+        if let Ok(header) = serde_json::from_str::<Header>(first_line) {
+            version = header.version;
+            lines.next();
+        }
+    }
+    let mut records: Vec<SongRecord> = vec![];
+    let mut errors: Vec<ParseError> = vec![];
+    if version > 2 {
+        errors.push(ParseError {
+            line: 1,
+            message: format!("Unsupported search file version {}.", version),
+        });
+        return (records, errors);
+    }
+    for (i, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = match version {
+            2 => parse_json_line(line),
+            _ => parse_legacy_line(line),
+        };
+        match record {
+            Ok(record) => records.push(record),
+            Err(message) => errors.push(ParseError {
+                line: i + 1,
+                message,
+            }),
+        }
+    }
+    (records, errors)
+}
+
+fn parse_json_line(line: &str) -> Result<SongRecord, String> {
+    let record: SongRecord = serde_json::from_str(line).map_err(|err| err.to_string())?;
+    if record.name.is_empty() {
+        return Err(String::from("The name of the song is missing."));
+    }
+    Ok(record)
+}
+
+fn parse_legacy_line(line: &str) -> Result<SongRecord, String> {
+    let s_line: Vec<&str> = line.splitn(3, ':').collect();
+    if s_line.len() < 3 || s_line[0].is_empty() {
+        return Err(String::from(
+            "Expected <filename_without_extension>:<song_title>:<song_lyrics>.",
+        ));
+    }
+    Ok(SongRecord {
+        name: s_line[0].to_string(),
+        title: s_line[1].to_string(),
+        lyrics: s_line[2].to_string(),
+        is_legacy: true,
+        ..Default::default()
+    })
+}
+
+pub fn write(records: &[SongRecord]) -> String {
+    let mut content = String::from(HEADER);
+    content.push('\n');
+    for record in records {
+        content.push_str(&serde_json::to_string(record).unwrap());
+        content.push('\n');
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SongRecord;
+
+    fn get_lines(errors: &[super::ParseError]) -> Vec<usize> {
+        errors.iter().map(|err| err.line).collect()
+    }
+
+    #[test]
+    fn version_2_lines_can_contain_colons() {
+        let (records, errors) = super::parse(concat!(
+            r#"{"version":2}"#,
+            "\n",
+            r#"{"name":"Psalm_23","title":"Psalm 23: The Lord is my shepherd","lyrics":"He says:\nI"}"#,
+            "\n"
+        ));
+        assert!(errors.is_empty());
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].name, "Psalm_23");
+        assert_eq!(records[0].title, "Psalm 23: The Lord is my shepherd");
+        assert_eq!(records[0].lyrics, "He says:\nI");
+        assert!(!records[0].is_legacy);
+    }
+
+    #[test]
+    fn files_without_header_are_legacy() {
+        let (records, errors) = super::parse("Psalm_23:psalm 23:the lord: my shepherd\n");
+        assert!(errors.is_empty());
+        assert_eq!(records[0].title, "psalm 23");
+        // only the first two colons separate
+        assert_eq!(records[0].lyrics, "the lord: my shepherd");
+        assert!(records[0].is_legacy);
+    }
+
+    #[test]
+    fn unsupported_versions_are_an_error() {
+        let (records, errors) =
+            super::parse("{\"version\":3}\n{\"name\":\"Song\",\"title\":\"Song\"}\n");
+        assert!(records.is_empty());
+        assert_eq!(get_lines(&errors), vec![1]);
+    }
+
+    #[test]
+    fn malformed_lines_are_errors_with_their_line_number() {
+        let (records, errors) = super::parse(concat!(
+            "{\"version\":2}\n",
+            "{\"name\":\"Song\",\"title\":\"Song\"}\n",
+            "\n",
+            "{\"name\":\"Broken\"\n",
+            "{\"name\":\"\",\"title\":\"No name\"}\n",
+        ));
+        assert_eq!(records.len(), 1);
+        assert_eq!(get_lines(&errors), vec![4, 5]);
+        let (records, errors) = super::parse("Song:song:lyrics\nno colons\n");
+        assert_eq!(records.len(), 1);
+        assert_eq!(get_lines(&errors), vec![2]);
+    }
+
+    #[test]
+    fn written_files_are_read_again() {
+        let record = SongRecord {
+            name: String::from("Song"),
+            title: String::from("Title: with a colon"),
+            author: Some(String::from("Author")),
+            lyrics: String::from("line 1\nline 2"),
+            ..Default::default()
+        };
+        let (records, errors) = super::parse(&super::write(&[record]));
+        assert!(errors.is_empty());
+        assert_eq!(records[0].title, "Title: with a colon");
+        assert_eq!(records[0].author.as_deref(), Some("Author"));
+        assert_eq!(records[0].lyrics, "line 1\nline 2");
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::normalize;
+use crate::song_index::SongFile;
+use crate::state;
+
+const SLUGS_FILE: &str = "slugs.json";
+
+/*
+ * Telegram only recognizes commands of at
+ * most 32 latin letters, digits and underscores.
+*/
+const MAX_COMMAND_LEN: usize = 32;
+
+// commands of the bot itself, no song may take them
+const RESERVED: [&str; 5] = ["start", "list", "report", "cancel", "language"];
+
+/*
+ * The slug every song got, by its path relative to the songs folder.
+ * Once a song has a slug it keeps it, even if a file that would
+ * get the same slug is added later. The slugs of removed songs
+ * stay reserved so that old commands don't send another song.
+ * Without --data-path it's only kept until songy is stopped.
+*/
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct SlugRegistry {
+    slugs: BTreeMap<PathBuf, String>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl SlugRegistry {
+    pub fn load(data_path: Option<&String>) -> Self {
+        let (mut registry, path) = state::load_json::<Self>(data_path, SLUGS_FILE);
+        registry.path = path;
+        registry
+    }
+
+    fn save(&self) {
+        if let Some(path) = self.path.as_ref() {
+            state::save_json(path, self);
+        }
+    }
+}
+
+/*
+ * Gives every song a unique command (slug).
+ * New songs are handled in the order of the songs, which
+ * have to be sorted, so that colliding songs get their slug
+ * deterministically: the first one keeps the plain slug,
+ * the others in a folder get the folder in front, e.g. /en_Song,
+ * and if that's taken too _2, _3, ... appended.
+ * Returns warnings about the songs whose
+ * file name can't be used as command directly.
+*/
+pub fn assign_slugs(
+    songs: &mut [SongFile],
+    folder_names: &[String],
+    songs_path: &Path,
+    registry: &mut SlugRegistry,
+) -> Vec<String> {
+    let mut taken: HashSet<String> = RESERVED.iter().map(|name| name.to_string()).collect();
+    taken.extend(folder_names.iter().map(|name| name.to_lowercase()));
+    let get_key = |song: &SongFile| {
+        song.path
+            .strip_prefix(songs_path)
+            .unwrap_or(&song.path)
+            .to_path_buf()
+    };
+    for song in songs.iter_mut() {
+        if let Some(slug) = registry.slugs.get(&get_key(song)) {
+            // a folder that was added later takes precedence
+            if taken.insert(slug.to_lowercase()) {
+                song.slug = slug.clone();
+            }
+        }
+    }
+    taken.extend(registry.slugs.values().map(|slug| slug.to_lowercase()));
+    let mut is_changed = false;
+    let mut warnings: Vec<String> = vec![];
+    for song in songs.iter_mut() {
+        let stem = get_stem(&song.file_name);
+        let base = slugify(stem);
+        if song.slug.is_empty() {
+            let mut unique_base = base.clone();
+            if taken.contains(&base.to_lowercase()) {
+                if let Some(folder) = get_folder(&get_key(song)) {
+                    unique_base = slugify(&format!("{}_{}", folder, stem));
+                }
+            }
+            let mut slug = unique_base.clone();
+            let mut n = 2;
+            while taken.contains(&slug.to_lowercase()) {
+                let suffix = format!("_{}", n);
+                let base =
+                    truncate(&unique_base, MAX_COMMAND_LEN - suffix.len()).trim_end_matches('_');
+                slug = format!("{}{}", base, suffix);
+                n += 1;
+            }
+            taken.insert(slug.to_lowercase());
+            registry.slugs.insert(get_key(song), slug.clone());
+            song.slug = slug;
+            is_changed = true;
+        }
+        let slug = &song.slug;
+        if !is_valid_command(stem) {
+            warnings.push(format!(
+                "{}: \"{}\" is not a valid telegram command, it's sent as /{}.",
+                song.path.display(),
+                stem,
+                slug
+            ));
+        } else if *slug != base {
+            warnings.push(format!(
+                "{}: /{} is already taken, it's sent as /{}.",
+                song.path.display(),
+                base,
+                slug
+            ));
+        }
+    }
+    if is_changed {
+        registry.save();
+    }
+    warnings
+}
+
+pub fn is_valid_command(name: &str) -> bool {
+    (1..=MAX_COMMAND_LEN).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// the folder in the songs folder the song is in
+fn get_folder(key: &Path) -> Option<String> {
+    let mut components = key.components();
+    let folder = components.next()?;
+    components.next()?;
+    Some(folder.as_os_str().to_string_lossy().to_string())
+}
+
+/*
+ * The file name without its extension. Unlike
+ * SongFile::name other dots are kept, so that
+ * song.pdf and song.v2.pdf don't collide.
+*/
+pub fn get_stem(file_name: &str) -> &str {
+    match file_name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => file_name,
+    }
+}
+
+/*
+ * Cyrillic letters are transliterated, accents are dropped the
+ * way the search does it, just keeping the case, and everything
+ * else that's not allowed in a command becomes an underscore.
+*/
+fn slugify(name: &str) -> String {
+    let latin: String = name.chars().map(normalize::to_latin).collect();
+    let mut slug = String::new();
+    for c in normalize::remove_accents(&latin).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    let slug = truncate(&slug, MAX_COMMAND_LEN).trim_end_matches('_');
+    if slug.is_empty() {
+        return String::from("song");
+    }
+    slug.to_string()
+}
+
+// the slug only consists of ascii characters
+fn truncate(slug: &str, len: usize) -> &str {
+    &slug[..slug.len().min(len)]
+}
@@ -0,0 +1,164 @@
+use crate::normalize::Normalizer;
+use crate::song_index::SearchEntry;
+
+// the lines around a match are cut to this, see MAX_DESCRIPTION_LEN in main.rs
+const MAX_SNIPPET_LEN: usize = 50;
+// how much of the line is shown before the first match
+const CONTEXT_LEN: usize = 15;
+
+/*
+ * The line of the lyrics the first match is in,
+ * as it was written, with the matches in bold (html).
+ * The terms have to be normalized like the lyrics.
+*/
+pub fn get_snippet(
+    entry: &SearchEntry,
+    normalizer: Normalizer,
+    terms: &[String],
+) -> Option<String> {
+    // legacy search files have no lines
+    if entry.lyric_lines.is_empty() {
+        return None;
+    }
+    let (start, end) = terms
+        .iter()
+        .filter(|term| !term.is_empty())
+        .filter_map(|term| {
+            let start = entry.lyrics.find(term.as_str())?;
+            Some((start, start + term.len()))
+        })
+        .min()?;
+    let first_line = entry
+        .line_starts
+        .partition_point(|line_start| *line_start <= start)
+        - 1;
+    // line breaks aren't searched, so a match can go on in the next line
+    let last_line = entry
+        .line_starts
+        .partition_point(|line_start| *line_start < end)
+        .max(first_line + 1)
+        .min(first_line + 2);
+    let text = entry.lyric_lines[first_line..last_line].join(" / ");
+    let chars: Vec<char> = text.chars().collect();
+    let is_bold = get_matched_chars(&chars, normalizer, terms);
+    let first_bold = is_bold.iter().position(|is_bold| *is_bold)?;
+    // as much of the line as fits, starting with a whole word
+    let mut from = first_bold
+        .saturating_sub(CONTEXT_LEN)
+        .min(chars.len().saturating_sub(MAX_SNIPPET_LEN));
+    while from > 0 && from < first_bold && !chars[from - 1].is_whitespace() {
+        from += 1;
+    }
+    let to = chars.len().min(from + MAX_SNIPPET_LEN);
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    let mut in_bold = false;
+    for i in from..to {
+        if is_bold[i] != in_bold {
+            snippet.push_str(if is_bold[i] { "<b>" } else { "</b>" });
+            in_bold = is_bold[i];
+        }
+        snippet.push_str(&crate::escape_html(&chars[i].to_string()));
+    }
+    if in_bold {
+        snippet.push_str("</b>");
+    }
+    if to < chars.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+/*
+ * Normalizes the text char by char to know which of
+ * the chars as they were written are part of a match.
+ * Spaces and punctuation only count within a match.
+*/
+fn get_matched_chars(chars: &[char], normalizer: Normalizer, terms: &[String]) -> Vec<bool> {
+    let mut normalized = String::new();
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for c in chars {
+        let start = normalized.len();
+        normalized.push_str(&normalizer.for_fulltext_search(&c.to_string()));
+        ranges.push((start, normalized.len()));
+    }
+    let matches: Vec<(usize, usize)> = terms
+        .iter()
+        .filter(|term| !term.is_empty())
+        .flat_map(|term| {
+            normalized
+                .match_indices(term.as_str())
+                .map(|(i, term)| (i, i + term.len()))
+        })
+        .collect();
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            matches.iter().any(|(match_start, match_end)| {
+                if start == end {
+                    match_start < start && start < match_end
+                } else {
+                    start < match_end && end > match_start
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::normalize::Normalizer;
+    use crate::search_file::SongRecord;
+    use crate::song_index::SearchEntry;
+
+    fn get_snippet(lyrics: &str, search: &str) -> Option<String> {
+        let normalizer = Normalizer::default();
+        let record = SongRecord {
+            name: String::from("Song"),
+            lyrics: lyrics.to_string(),
+            ..Default::default()
+        };
+        let entry = SearchEntry::new(record, normalizer);
+        super::get_snippet(
+            &entry,
+            normalizer,
+            &[normalizer.for_fulltext_search(search)],
+        )
+    }
+
+    #[test]
+    fn matches_can_go_on_in_the_next_line() {
+        assert_eq!(
+            get_snippet(
+                "Amazing grace how sweet\nthe sound that saved",
+                "sweet the sound"
+            ),
+            Some(String::from(
+                "Amazing grace how <b>sweet / the sound</b> that saved"
+            ))
+        );
+    }
+
+    #[test]
+    fn lyrics_are_escaped() {
+        assert_eq!(
+            get_snippet("Tom & Jerry <3 love", "love"),
+            Some(String::from("Tom &amp; Jerry &lt;3 <b>love</b>"))
+        );
+    }
+
+    #[test]
+    fn long_lines_are_cut_around_the_match() {
+        assert_eq!(
+            get_snippet(
+                "one two three four five six seven eight nine ten eleven twelve thirteen fourteen",
+                "nine"
+            ),
+            Some(String::from(
+                "…seven eight <b>nine</b> ten eleven twelve thirteen fourte…"
+            ))
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::chordpro;
+use crate::normalize::Normalizer;
+use crate::pdf;
+use crate::search_file::{self, SongRecord};
+use crate::slug::{self, SlugRegistry};
+use crate::title_cache::TitleCache;
+
+#[derive(Clone)]
+pub struct SongFile {
+    pub path: PathBuf,
+    pub file_name: String,
+    // the bot command of the song, unique in the index
+    pub slug: String,
+    // from the search file, the chordpro file or the pdf
+    pub title: Option<String>,
+    pub author: Option<String>,
+}
+
+impl SongFile {
+    /*
+     * The part of the file name before the first dot,
+     * the search file refers to the song by it.
+     */
+    pub fn name(&self) -> &str {
+        self.file_name.split('.').next().unwrap()
+    }
+
+    /*
+     * The file name as it was written, if the command
+     * can't show it, e.g. because it's cyrillic.
+     */
+    pub fn original_name(&self) -> Option<String> {
+        let stem = slug::get_stem(&self.file_name);
+        if slug::is_valid_command(stem) {
+            return None;
+        }
+        Some(stem.replace('_', " "))
+    }
+
+    /*
+     * What lists show next to the command,
+     * e.g. "Love Me Tender (Elvis Presley)".
+     */
+    pub fn description(&self) -> Option<String> {
+        let title = self.title.clone().or_else(|| self.original_name());
+        match (title, self.author.as_ref()) {
+            (Some(title), Some(author)) => Some(format!("{} ({})", title, author)),
+            (None, Some(author)) => Some(format!("{} ({})", self.name().replace('_', " "), author)),
+            (title, None) => title,
+        }
+    }
+}
+
+/*
+ * A song of the search file with all
+ * searchable text already normalized.
+ * Keywords are the author, composer and tags.
+*/
+pub struct SearchEntry {
+    pub name: String,
+    pub title: String,
+    pub alternative_titles: Vec<String>,
+    pub keywords: Vec<String>,
+    pub hymnal_number: Option<String>,
+    pub lyrics: String,
+    // the lines of the lyrics as they were written, for snippets
+    pub lyric_lines: Vec<String>,
+    // where each of the lyric_lines starts in lyrics
+    pub line_starts: Vec<usize>,
+}
+
+impl SearchEntry {
+    pub fn new(record: SongRecord, normalizer: Normalizer) -> Self {
+        let normalize = |s: &String| normalizer.for_fulltext_search(s);
+        let mut keywords: Vec<String> = vec![];
+        keywords.extend(record.author.iter().map(normalize));
+        keywords.extend(record.composer.iter().map(normalize));
+        keywords.extend(record.tags.iter().map(normalize));
+        let mut lyrics = String::new();
+        let mut lyric_lines: Vec<String> = vec![];
+        let mut line_starts: Vec<usize> = vec![];
+        if record.is_legacy {
+            // already shrunk, there are no lines to show
+            lyrics = normalize(&record.lyrics);
+        } else {
+            for line in record.lyrics.lines().map(str::trim) {
+                if line.is_empty() {
+                    continue;
+                }
+                line_starts.push(lyrics.len());
+                lyrics.push_str(&normalizer.for_fulltext_search(line));
+                lyric_lines.push(line.to_string());
+            }
+        }
+        Self {
+            title: normalize(&record.title),
+            alternative_titles: record.alternative_titles.iter().map(normalize).collect(),
+            keywords,
+            hymnal_number: record.hymnal_number,
+            lyrics,
+            lyric_lines,
+            line_starts,
+            name: record.name,
+        }
+    }
+
+    pub fn titles(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.title)
+            .chain(self.alternative_titles.iter())
+            .chain(self.keywords.iter())
+    }
+}
+
+/*
+ * Holds everything the search needs in memory
+ * so that no message has to walk the songs folder
+ * or read the search file again.
+*/
+pub struct SongIndex {
+    songs_path: String,
+    search_file: Option<String>,
+    // how the songs and search strings are compared
+    pub normalizer: Normalizer,
+    pub songs: Vec<SongFile>,
+    pub folder_names: Vec<String>,
+    pub search_entries: Option<Vec<SearchEntry>>,
+    // about song files that can't be sent by their name
+    pub warnings: Vec<String>,
+    mtimes: Vec<(PathBuf, Option<SystemTime>)>,
+    titles: TitleCache,
+    slugs: SlugRegistry,
+}
+
+impl SongIndex {
+    pub fn new(
+        songs_path: &str,
+        search_file: Option<&String>,
+        data_path: Option<&String>,
+        normalizer: Normalizer,
+    ) -> Self {
+        let titles = TitleCache::load(data_path);
+        let slugs = SlugRegistry::load(data_path);
+        Self::create(songs_path, search_file, normalizer, titles, slugs)
+    }
+
+    fn create(
+        songs_path: &str,
+        search_file: Option<&String>,
+        normalizer: Normalizer,
+        titles: TitleCache,
+        slugs: SlugRegistry,
+    ) -> Self {
+        let mut index = Self {
+            songs_path: songs_path.to_string(),
+            search_file: search_file.cloned(),
+            normalizer,
+            songs: vec![],
+            folder_names: vec![],
+            search_entries: None,
+            warnings: vec![],
+            mtimes: vec![],
+            titles,
+            slugs,
+        };
+        index.build();
+        index
+    }
+
+    /*
+     * A new index if anything changed since this one was built.
+     * Adding, removing or renaming a file changes
+     * the mtime of the folder it's in, so only the
+     * folders and the search file have to be checked.
+     * The titles and slugs are taken over, so the files
+     * aren't read and the slug registry isn't loaded again.
+     */
+    pub fn refreshed(&self) -> Option<SongIndex> {
+        let changed = self
+            .mtimes
+            .iter()
+            .any(|(path, mtime)| get_mtime(path) != *mtime);
+        if !changed {
+            return None;
+        }
+        Some(SongIndex::create(
+            &self.songs_path,
+            self.search_file.as_ref(),
+            self.normalizer,
+            self.titles.clone(),
+            self.slugs.clone(),
+        ))
+    }
+
+    /*
+     * All songs with this name, there can be
+     * more than one in different folders.
+     */
+    pub fn get_songs_by_name(&self, name: &str) -> Vec<SongFile> {
+        let name = name.to_lowercase();
+        self.songs
+            .iter()
+            .filter(|song| song.name().to_lowercase() == name)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_song_by_slug(&self, slug: &str) -> Option<&SongFile> {
+        let slug = slug.to_lowercase();
+        self.songs
+            .iter()
+            .find(|song| song.slug.to_lowercase() == slug)
+    }
+
+    pub fn songs_in_folder(&self, folder_name: &str) -> Vec<SongFile> {
+        let folder = Path::new(&self.songs_path).join(folder_name);
+        self.songs
+            .iter()
+            .filter(|song| song.path.starts_with(&folder))
+            .cloned()
+            .collect()
+    }
+
+    fn build(&mut self) {
+        let songs_path = PathBuf::from(&self.songs_path);
+        self.songs = vec![];
+        self.folder_names = vec![];
+        self.mtimes = vec![(songs_path.clone(), get_mtime(&songs_path))];
+        read_songs_dir(self, &songs_path, true);
+        self.songs
+            .sort_by_key(|song| (song.file_name.to_lowercase(), song.path.clone()));
+        self.folder_names.sort();
+        set_titles_of_files(&mut self.songs, &mut self.titles);
+        let song_paths: HashSet<&Path> =
+            self.songs.iter().map(|song| song.path.as_path()).collect();
+        self.titles.save(&song_paths);
+        self.warnings = slug::assign_slugs(
+            &mut self.songs,
+            &self.folder_names,
+            &songs_path,
+            &mut self.slugs,
+        );
+        self.search_entries = None;
+        if let Some(search_file) = self.search_file.as_ref() {
+            let path = PathBuf::from(search_file);
+            self.mtimes.push((path.clone(), get_mtime(&path)));
+            if let Ok(content) = fs::read_to_string(&path) {
+                let (records, errors) = search_file::parse(&content);
+                for err in errors {
+                    eprintln!("{}:{}: {}", search_file, err.line, err.message);
+                }
+                set_titles_of_records(&mut self.songs, &records);
+                self.search_entries = Some(
+                    records
+                        .into_iter()
+                        .map(|record| SearchEntry::new(record, self.normalizer))
+                        .collect(),
+                );
+            }
+        }
+    }
+}
+
+fn read_songs_dir(index: &mut SongIndex, folder_path: &Path, is_root: bool) {
+    match fs::read_dir(folder_path) {
+        Ok(read_dir) => {
+            for r in read_dir {
+                match r {
+                    Ok(dir_entry) => {
+                        let path = dir_entry.path();
+                        if dir_entry.file_type().unwrap().is_dir() {
+                            if is_root {
+                                let name = dir_entry.file_name().to_str().unwrap().to_string();
+                                index.folder_names.push(name);
+                            }
+                            index.mtimes.push((path.clone(), get_mtime(&path)));
+                            read_songs_dir(index, &path, false);
+                        } else {
+                            index.songs.push(SongFile {
+                                file_name: dir_entry.file_name().to_str().unwrap().to_string(),
+                                path,
+                                slug: String::new(),
+                                title: None,
+                                author: None,
+                            });
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("Cannot access filepath.");
+                        dbg!(err);
+                    }
+                }
+            }
+        }
+        Err(_) => {
+            eprintln!(
+                "Cannot open/read or what ever the path {}.",
+                folder_path.display()
+            );
+        }
+    }
+}
+
+/*
+ * Chordpro files have their title in it, pdf files maybe in their metadata.
+ * Titles that are just the file name aren't worth showing.
+*/
+fn set_titles_of_files(songs: &mut [SongFile], titles: &mut TitleCache) {
+    for song in songs.iter_mut() {
+        let (title, author) = if chordpro::is_chordpro_file(&song.path) {
+            titles.get(&song.path, || match fs::read_to_string(&song.path) {
+                Ok(content) => {
+                    let record = chordpro::parse(song.name(), &content);
+                    (Some(record.title), record.author)
+                }
+                Err(_) => (None, None),
+            })
+        } else if pdf::is_pdf_file(&song.path) {
+            titles.get(&song.path, || pdf::read_info(&song.path))
+        } else {
+            (None, None)
+        };
+        song.title = title.filter(|title| title != song.name());
+        song.author = author;
+    }
+}
+
+/*
+ * The search file has precedence over the files themselves.
+*/
+fn set_titles_of_records(songs: &mut [SongFile], records: &[SongRecord]) {
+    for record in records.iter().filter(|record| !record.is_legacy) {
+        let name = record.name.to_lowercase();
+        for song in songs
+            .iter_mut()
+            .filter(|song| song.name().to_lowercase() == name)
+        {
+            song.title = Some(record.title.clone());
+            if record.author.is_some() {
+                song.author = record.author.clone();
+            }
+        }
+    }
+}
+
+fn get_mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::dialog::Dialogs;
+
+const STATE_FILE: &str = "state.json";
+
+/*
+ * What songy has to remember across restarts.
+ * Without --data-path it's only kept in memory.
+*/
+#[derive(Serialize, Deserialize, Default)]
+pub struct State {
+    // the first update that hasn't been handled, getUpdates starts there
+    #[serde(default)]
+    pub offset: Option<i64>,
+    #[serde(default)]
+    pub dialogs: Dialogs,
+    // the language every user chose with /language
+    #[serde(default)]
+    pub languages: HashMap<u64, String>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl State {
+    pub fn load(data_path: Option<&String>) -> Self {
+        let (mut state, path) = load_json::<Self>(data_path, STATE_FILE);
+        state.path = path;
+        state
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = self.path.as_ref() {
+            save_json(path, self);
+        }
+    }
+}
+
+/*
+ * Returns the default if the file doesn't exist yet
+ * and the path to save to if there is a --data-path.
+*/
+pub fn load_json<T: DeserializeOwned + Default>(
+    data_path: Option<&String>,
+    file_name: &str,
+) -> (T, Option<PathBuf>) {
+    let Some(data_path) = data_path else {
+        return (T::default(), None);
+    };
+    if let Err(err) = fs::create_dir_all(data_path) {
+        eprintln!("Cannot create data path {}.", data_path);
+        dbg!(err);
+        process::exit(-1);
+    }
+    let path = PathBuf::from(data_path).join(file_name);
+    let value = match fs::read_to_string(&path) {
+        Ok(content) => match serde_json::from_str(&content) {
+            Ok(value) => value,
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                process::exit(-1);
+            }
+        },
+        Err(_) => T::default(),
+    };
+    (value, Some(path))
+}
+
+/*
+ * Written to a temporary file first so that a crash
+ * while writing doesn't leave a broken file.
+*/
+pub fn save_json<T: Serialize>(path: &Path, value: &T) {
+    let tmp_path = path.with_extension("json.tmp");
+    let content = serde_json::to_string(value).unwrap();
+    if let Err(err) = fs::write(&tmp_path, content).and_then(|_| fs::rename(&tmp_path, path)) {
+        eprintln!("Saving {} failed.", path.display());
+        dbg!(err);
+    }
+}
@@ -0,0 +1,589 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{env, fs, process, thread};
+
+use frankenstein::api_params::{File, InlineQueryResult, ReplyMarkup};
+use frankenstein::objects::{
+    CallbackQuery, Chat, ChatType, InlineKeyboardMarkup, InlineQuery, InputMessageContent, Update,
+    UpdateContent, User,
+};
+use frankenstein::Message;
+
+use crate::i18n::I18n;
+use crate::state::State;
+use crate::transport::{Recorder, Sent};
+use crate::{Config, HandleArg};
+
+const CHAT_ID: i64 = 1;
+const USER_ID: u64 = 1;
+
+/*
+ * The bot with a songs folder of its own,
+ * everything it sends is recorded.
+*/
+struct Bot {
+    handle_arg: HandleArg,
+    recorder: Arc<Recorder>,
+    songs_path: PathBuf,
+    reports_path: PathBuf,
+    next_id: u32,
+}
+
+impl Bot {
+    // the files are created empty, relative to the songs folder
+    fn new(name: &str, files: &[&str]) -> Self {
+        Self::create(name, files, None)
+    }
+
+    fn with_search_file(name: &str, files: &[&str], search_file: &str) -> Self {
+        Self::create(name, files, Some(search_file))
+    }
+
+    fn create(name: &str, files: &[&str], search_file: Option<&str>) -> Self {
+        let dir = env::temp_dir().join(format!("songy-test-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        let songs_path = dir.join("songs");
+        let reports_path = dir.join("reports");
+        fs::create_dir_all(&reports_path).unwrap();
+        for file in files {
+            let path = songs_path.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let mut config = Config::new();
+        if let Some(content) = search_file {
+            let path = dir.join("search.txt");
+            fs::write(&path, content).unwrap();
+            config.search_file = Some(path.to_str().unwrap().to_string());
+        }
+        config.songs_path = Some(songs_path.to_str().unwrap().to_string());
+        config.reports_path = Some(reports_path.to_str().unwrap().to_string());
+        config.lang = Some(String::from("en"));
+        let recorder = Arc::new(Recorder::new());
+        let handle_arg = crate::get_handle_arg(
+            &config,
+            recorder.clone(),
+            Some(String::from("songybot")),
+            State::default(),
+        );
+        Self {
+            handle_arg,
+            recorder,
+            songs_path,
+            reports_path,
+            next_id: 0,
+        }
+    }
+
+    // returns everything the bot sent in answer
+    fn send(&mut self, content: UpdateContent) -> Vec<Sent> {
+        let update = Update {
+            update_id: self.next_id,
+            content,
+        };
+        self.next_id += 1;
+        crate::handle_update(&mut self.handle_arg, &update);
+        self.recorder.take_sent()
+    }
+
+    fn send_text(&mut self, text: &str) -> Vec<Sent> {
+        let msg = get_message(self.next_id as i32, text);
+        self.send(UpdateContent::Message(msg))
+    }
+
+    fn press(&mut self, data: &str) -> Vec<Sent> {
+        let query = CallbackQuery::builder()
+            .id(self.next_id.to_string())
+            .from(get_user())
+            .message(get_message(0, ""))
+            .chat_instance("chat")
+            .data(data)
+            .build();
+        self.send(UpdateContent::CallbackQuery(query))
+    }
+
+    fn song(&self, file: &str) -> PathBuf {
+        self.songs_path.join(file)
+    }
+
+    fn reports(&self) -> Vec<String> {
+        fs::read_dir(&self.reports_path)
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect()
+    }
+}
+
+fn get_user() -> User {
+    User::builder()
+        .id(USER_ID)
+        .is_bot(false)
+        .first_name("test")
+        .build()
+}
+
+fn get_message(message_id: i32, text: &str) -> Message {
+    Message::builder()
+        .message_id(message_id)
+        .date(0)
+        .chat(
+            Chat::builder()
+                .id(CHAT_ID)
+                .type_field(ChatType::Private)
+                .build(),
+        )
+        .from(get_user())
+        .text(text)
+        .build()
+}
+
+// the texts of the sent and edited messages
+fn texts(sent: &[Sent]) -> Vec<String> {
+    sent.iter()
+        .filter_map(|sent| match sent {
+            Sent::Message(params) => Some(params.text.clone()),
+            Sent::EditedMessage(params) => Some(params.text.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn documents(sent: &[Sent]) -> Vec<PathBuf> {
+    sent.iter()
+        .filter_map(|sent| match sent {
+            Sent::Document(params) => match &params.document {
+                File::InputFile(input_file) => Some(input_file.path.clone()),
+                File::String(_) => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+// (text, callback data) of every button
+fn buttons(sent: &[Sent]) -> Vec<(String, String)> {
+    let keyboards: Vec<&InlineKeyboardMarkup> = sent
+        .iter()
+        .filter_map(|sent| match sent {
+            Sent::Message(params) => match params.reply_markup.as_ref() {
+                Some(ReplyMarkup::InlineKeyboardMarkup(keyboard)) => Some(keyboard),
+                _ => None,
+            },
+            Sent::EditedMessage(params) => params.reply_markup.as_ref(),
+            _ => None,
+        })
+        .collect();
+    keyboards
+        .iter()
+        .flat_map(|keyboard| keyboard.inline_keyboard.iter().flatten())
+        .map(|button| {
+            (
+                button.text.clone(),
+                button.callback_data.clone().unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+fn get_button_data(sent: &[Sent], text: &str) -> String {
+    buttons(sent)
+        .into_iter()
+        .find(|(button_text, _)| button_text == text)
+        .map(|(_, data)| data)
+        .unwrap()
+}
+
+fn get_i18n(bot: &Bot, lang: &str) -> I18n {
+    I18n::new(
+        &bot.handle_arg.translations,
+        lang,
+        &bot.handle_arg.index.folder_names,
+    )
+}
+
+#[test]
+fn start_lists_the_commands() {
+    let mut bot = Bot::new("start", &["en/Amazing_Grace.pdf"]);
+    let sent = bot.send_text("/start");
+    assert_eq!(texts(&sent), vec![bot.handle_arg.i18n.start_msg.clone()]);
+    assert!(bot.handle_arg.i18n.start_msg.contains("/en"));
+}
+
+#[test]
+fn commands_can_be_addressed_to_the_bot() {
+    let mut bot = Bot::new("addressed", &["en/Amazing_Grace.pdf"]);
+    let sent = bot.send_text("/start@songybot");
+    assert_eq!(texts(&sent), vec![bot.handle_arg.i18n.start_msg.clone()]);
+    let sent = bot.send_text("/start@SongyBot");
+    assert_eq!(texts(&sent), vec![bot.handle_arg.i18n.start_msg.clone()]);
+}
+
+#[test]
+fn commands_to_other_bots_are_ignored() {
+    let mut bot = Bot::new("other-bot", &["en/Amazing_Grace.pdf"]);
+    assert!(bot.send_text("/start@otherbot").is_empty());
+    assert!(bot.send_text("/list@otherbot").is_empty());
+    bot.send_text("/report");
+    assert!(bot.send_text("/cancel@otherbot").is_empty());
+    bot.send_text("it's still reported");
+    assert_eq!(bot.reports().len(), 1);
+}
+
+#[test]
+fn list_shows_every_song_with_a_button() {
+    let mut bot = Bot::new("list", &["en/Amazing_Grace.pdf", "de/Stille_Nacht.pdf"]);
+    let sent = bot.send_text("/list");
+    let text = &texts(&sent)[0];
+    assert!(text.contains("1. /Amazing_Grace"));
+    assert!(text.contains("2. /Stille_Nacht"));
+    let buttons: Vec<String> = buttons(&sent).into_iter().map(|(text, _)| text).collect();
+    assert_eq!(buttons, vec!["1", "2"]);
+}
+
+#[test]
+fn folder_command_lists_the_songs_of_the_folder() {
+    let mut bot = Bot::new("folder", &["en/Amazing_Grace.pdf", "de/Stille_Nacht.pdf"]);
+    let text = &texts(&bot.send_text("/de"))[0];
+    assert!(text.contains("/Stille_Nacht"));
+    assert!(!text.contains("/Amazing_Grace"));
+}
+
+#[test]
+fn song_command_sends_the_song() {
+    let mut bot = Bot::new("command", &["en/Amazing_Grace.pdf"]);
+    let sent = bot.send_text("/Amazing_Grace");
+    assert_eq!(documents(&sent), vec![bot.song("en/Amazing_Grace.pdf")]);
+}
+
+#[test]
+fn commands_of_removed_songs_are_not_given_away() {
+    let mut bot = Bot::new("removed", &["en/Song.pdf", "fr/Song.pdf"]);
+    fs::remove_file(bot.song("en/Song.pdf")).unwrap();
+    crate::refresh_index(&mut bot.handle_arg);
+    assert_eq!(bot.handle_arg.index.songs.len(), 1);
+    let sent = bot.send_text("/fr_Song");
+    assert_eq!(documents(&sent), vec![bot.song("fr/Song.pdf")]);
+    assert!(bot.handle_arg.index.get_song_by_slug("Song").is_none());
+}
+
+#[test]
+fn commands_are_made_up_for_other_file_names() {
+    let mut bot = Bot::new("made-up", &["Été 2.pdf", "Любовь.pdf", "Doğan.pdf"]);
+    let mut slugs: Vec<&str> = bot
+        .handle_arg
+        .index
+        .songs
+        .iter()
+        .map(|song| song.slug.as_str())
+        .collect();
+    slugs.sort();
+    assert_eq!(slugs, vec!["Dogan", "Ete_2", "Lyubov"]);
+    let sent = bot.send_text("/Dogan");
+    assert_eq!(documents(&sent), vec![bot.song("Doğan.pdf")]);
+}
+
+#[test]
+fn unknown_command_is_not_found() {
+    let mut bot = Bot::new("unknown", &["en/Amazing_Grace.pdf"]);
+    let sent = bot.send_text("/Yesterday");
+    assert_eq!(
+        texts(&sent),
+        vec![bot.handle_arg.i18n.song_not_found.clone()]
+    );
+}
+
+#[test]
+fn song_button_sends_the_song() {
+    let mut bot = Bot::new(
+        "song-button",
+        &["en/Amazing_Grace.pdf", "en/Love_Me_Tender.pdf"],
+    );
+    let sent = bot.send_text("/list");
+    let sent = bot.press(&get_button_data(&sent, "2"));
+    assert!(matches!(sent[0], Sent::CallbackQueryAnswer(_)));
+    assert_eq!(documents(&sent), vec![bot.song("en/Love_Me_Tender.pdf")]);
+}
+
+#[test]
+fn pages_are_turned_by_editing_the_list() {
+    let files: Vec<String> = (1..=25).map(|i| format!("Song_{:02}.pdf", i)).collect();
+    let files: Vec<&str> = files.iter().map(String::as_str).collect();
+    let mut bot = Bot::new("pages", &files);
+    let sent = bot.send_text("/list");
+    let text = &texts(&sent)[0];
+    assert!(text.starts_with(&format!("{} 1/2", bot.handle_arg.i18n.page)));
+    assert!(text.contains("20. /Song_20"));
+    assert!(!text.contains("/Song_21"));
+    let sent = bot.press(&get_button_data(&sent, "»"));
+    assert!(matches!(sent[1], Sent::EditedMessage(_)));
+    let text = &texts(&sent)[0];
+    assert!(text.starts_with(&format!("{} 2/2", bot.handle_arg.i18n.page)));
+    assert!(text.contains("21. /Song_21"));
+    assert!(get_button_data(&sent, "«").ends_with(":0"));
+    let sent = bot.press(&get_button_data(&sent, "25"));
+    assert_eq!(documents(&sent), vec![bot.song("Song_25.pdf")]);
+}
+
+#[test]
+fn buttons_of_unknown_lists_have_expired() {
+    let mut bot = Bot::new("expired", &["en/Amazing_Grace.pdf"]);
+    let sent = bot.press("song:99:0");
+    let Sent::CallbackQueryAnswer(params) = &sent[0] else {
+        panic!("the button wasn't answered");
+    };
+    assert_eq!(params.text, Some(bot.handle_arg.i18n.list_expired.clone()));
+    assert_eq!(sent.len(), 1);
+}
+
+#[test]
+fn title_search_lists_the_matches() {
+    let mut bot = Bot::new("search", &["en/Amazing_Grace.pdf", "en/Love_Me_Tender.pdf"]);
+    let sent = bot.send_text("love tender");
+    let text = &texts(&sent)[0];
+    assert!(text.contains("/Love_Me_Tender"));
+    assert!(!text.contains("/Amazing_Grace"));
+}
+
+#[test]
+fn title_search_lists_every_exact_match() {
+    let mut bot = Bot::new(
+        "exact",
+        &["de/Love_Me.pdf", "en/Love_Me.pdf", "en/Love_Me_Tender.pdf"],
+    );
+    let text = &texts(&bot.send_text("love me"))[0];
+    assert!(text.contains("1. /Love_Me\n"));
+    assert!(text.contains("2. /en_Love_Me\n"));
+    assert!(text.contains("3. /Love_Me_Tender"));
+}
+
+#[test]
+fn single_fulltext_hit_is_sent() {
+    let mut bot = Bot::with_search_file(
+        "fulltext",
+        &["en/Amazing_Grace.pdf", "en/Love_Me_Tender.pdf"],
+        "Love_Me_Tender:love me tender:love me tender love me sweet\n",
+    );
+    let sent = bot.send_text("sweet");
+    assert_eq!(documents(&sent), vec![bot.song("en/Love_Me_Tender.pdf")]);
+}
+
+#[test]
+fn fulltext_hit_without_file_is_not_found() {
+    let mut bot = Bot::with_search_file(
+        "fulltext-missing",
+        &["en/Love_Me_Tender.pdf"],
+        "Love:love:all you need is love\n",
+    );
+    let sent = bot.send_text("all you need");
+    assert!(documents(&sent).is_empty());
+    assert_eq!(
+        texts(&sent),
+        vec![bot.handle_arg.i18n.song_not_found.clone()]
+    );
+}
+
+#[test]
+fn fulltext_hit_in_several_folders_is_listed() {
+    let mut bot = Bot::with_search_file(
+        "fulltext-folders",
+        &["de/Love_Me.pdf", "en/Love_Me.pdf"],
+        "Love_Me:love me:love me do\n",
+    );
+    let sent = bot.send_text("love me do");
+    assert!(documents(&sent).is_empty());
+    let text = &texts(&sent)[0];
+    assert!(text.contains("1. /Love_Me\n"));
+    assert!(text.contains("2. /en_Love_Me\n"));
+}
+
+#[test]
+fn report_is_saved() {
+    let mut bot = Bot::new("report", &["en/Amazing_Grace.pdf"]);
+    let report = bot.handle_arg.i18n.report.clone();
+    assert_eq!(texts(&bot.send_text("/report")), vec![report.msg]);
+    let sent = bot.send_text("Wrong chord in Amazing Grace");
+    assert_eq!(texts(&sent), vec![report.success_msg]);
+    assert_eq!(bot.reports(), vec!["Wrong chord in Amazing Grace\n"]);
+    // the dialog is over, so the next message is a search again
+    let sent = bot.send_text("/Amazing_Grace");
+    assert_eq!(documents(&sent).len(), 1);
+}
+
+#[test]
+fn report_can_be_cancelled() {
+    let mut bot = Bot::new("cancel", &["en/Amazing_Grace.pdf"]);
+    bot.send_text("/report");
+    let sent = bot.send_text("/cancel");
+    assert_eq!(
+        texts(&sent),
+        vec![bot.handle_arg.i18n.report.cancel_msg.clone()]
+    );
+    assert!(bot.reports().is_empty());
+    let sent = bot.send_text("/Amazing_Grace");
+    assert_eq!(documents(&sent).len(), 1);
+}
+
+#[test]
+fn cancel_can_be_addressed_to_the_bot() {
+    let mut bot = Bot::new("cancel-addressed", &["en/Amazing_Grace.pdf"]);
+    bot.send_text("/report");
+    let sent = bot.send_text("/cancel@songybot");
+    assert_eq!(
+        texts(&sent),
+        vec![bot.handle_arg.i18n.report.cancel_msg.clone()]
+    );
+    assert!(bot.reports().is_empty());
+}
+
+#[test]
+fn commands_end_the_report() {
+    let mut bot = Bot::new("report-command", &["en/Amazing_Grace.pdf"]);
+    bot.send_text("/report");
+    let sent = bot.send_text("/Amazing_Grace@songybot");
+    assert_eq!(
+        texts(&sent),
+        vec![bot.handle_arg.i18n.report.cancel_msg.clone()]
+    );
+    assert_eq!(documents(&sent), vec![bot.song("en/Amazing_Grace.pdf")]);
+    assert!(bot.reports().is_empty());
+}
+
+#[test]
+fn reports_of_the_same_time_are_all_kept() {
+    let bot = Bot::new("report-names", &[]);
+    let reports_path = bot.reports_path.to_str().unwrap().to_string();
+    for i in 0..3 {
+        crate::save_file(crate::ReportFileType::Text(i.to_string()), &reports_path);
+    }
+    let mut reports = bot.reports();
+    reports.sort();
+    assert_eq!(reports, vec!["0\n", "1\n", "2\n"]);
+}
+
+#[test]
+fn language_is_chosen_with_buttons() {
+    let mut bot = Bot::new("language", &["en/Amazing_Grace.pdf"]);
+    let sent = bot.send_text("/language");
+    assert_eq!(texts(&sent), vec![bot.handle_arg.i18n.language_msg.clone()]);
+    let data = get_button_data(&sent, "Deutsch");
+    assert_eq!(data, "lang:de");
+    let sent = bot.press(&data);
+    assert!(matches!(sent[0], Sent::CallbackQueryAnswer(_)));
+    let de = get_i18n(&bot, "de");
+    assert_eq!(texts(&sent), vec![de.language_set]);
+    // the choice is kept for the next messages
+    assert_eq!(texts(&bot.send_text("/start")), vec![de.start_msg]);
+}
+
+#[test]
+fn inline_query_answers_with_the_song_commands() {
+    let mut bot = Bot::new("inline", &["en/Amazing_Grace.pdf", "en/Love_Me_Tender.pdf"]);
+    let query = InlineQuery::builder()
+        .id("query")
+        .from(get_user())
+        .query("amazing")
+        .offset("")
+        .build();
+    let sent = bot.send(UpdateContent::InlineQuery(query));
+    let Sent::InlineQueryAnswer(params) = &sent[0] else {
+        panic!("the inline query wasn't answered");
+    };
+    assert_eq!(params.results.len(), 1);
+    let InlineQueryResult::Article(article) = &params.results[0] else {
+        panic!("the song wasn't sent before, so it has to be a command");
+    };
+    let InputMessageContent::Text(content) = &article.input_message_content else {
+        panic!("the command has to be a text message");
+    };
+    assert_eq!(content.message_text, "/Amazing_Grace@songybot");
+}
+
+#[test]
+fn commands_are_registered_for_every_language() {
+    let bot = Bot::new("commands", &["en/Amazing_Grace.pdf", "Ab/Song.pdf"]);
+    crate::commands::register(&bot.handle_arg);
+    let sent = bot.recorder.take_sent();
+    let registered: Vec<(Option<String>, Vec<String>)> = sent
+        .iter()
+        .filter_map(|sent| match sent {
+            Sent::Commands(params) => Some((
+                params.language_code.clone(),
+                params
+                    .commands
+                    .iter()
+                    .map(|command| command.command.clone())
+                    .collect(),
+            )),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        registered.len(),
+        1 + bot.handle_arg.translations.languages().len()
+    );
+    for (_, commands) in &registered {
+        // Ab isn't lowercase, telegram would refuse it
+        assert_eq!(commands, &["start", "list", "language", "report", "en"]);
+    }
+    assert_eq!(registered[0].0, None);
+}
+
+#[test]
+fn offset_is_saved_once_the_updates_are_handled() {
+    let bot = Bot::new("offset", &["en/Amazing_Grace.pdf"]);
+    let workers = crate::workers::Workers::new(2);
+    // keeps the workers from handling the updates for now
+    let state = crate::lock(&bot.handle_arg.state);
+    for i in 5..8 {
+        let update = Update {
+            update_id: i,
+            content: UpdateContent::Message(get_message(i as i32, "/start")),
+        };
+        workers.dispatch(bot.handle_arg.clone(), update.clone());
+        // received again while it's waiting, it's only handled once
+        workers.dispatch(bot.handle_arg.clone(), update);
+    }
+    assert_eq!(state.offset, None);
+    drop(state);
+    let start = Instant::now();
+    while crate::lock(&bot.handle_arg.state).offset != Some(8) {
+        assert!(start.elapsed() < Duration::from_secs(10));
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(texts(&bot.recorder.take_sent()).len(), 3);
+}
+
+#[test]
+fn handled_updates_are_not_handled_again() {
+    let bot = Bot::new("handled", &["en/Amazing_Grace.pdf"]);
+    let workers = crate::workers::Workers::new(2);
+    let update = Update {
+        update_id: 5,
+        content: UpdateContent::Message(get_message(5, "/start")),
+    };
+    workers.dispatch(bot.handle_arg.clone(), update.clone());
+    let start = Instant::now();
+    while crate::lock(&bot.handle_arg.state).offset != Some(6) {
+        assert!(start.elapsed() < Duration::from_secs(10));
+        thread::sleep(Duration::from_millis(10));
+    }
+    // a getUpdates that was sent before the update was handled
+    workers.dispatch(bot.handle_arg.clone(), update);
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(texts(&bot.recorder.take_sent()).len(), 1);
+}
+
+#[test]
+fn poisoned_locks_are_still_used() {
+    let mut bot = Bot::new("poisoned", &["en/Amazing_Grace.pdf"]);
+    let state = bot.handle_arg.state.clone();
+    let _ = thread::spawn(move || {
+        let _state = state.lock().unwrap();
+        panic!("handling an update failed");
+    })
+    .join();
+    assert!(bot.handle_arg.state.is_poisoned());
+    let sent = bot.send_text("/start");
+    assert_eq!(texts(&sent), vec![bot.handle_arg.i18n.start_msg.clone()]);
+}
@@ -0,0 +1,113 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::file_cache::{self, Fingerprint};
+use crate::state;
+
+const TITLE_CACHE_FILE: &str = "titles.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedTitle {
+    title: Option<String>,
+    author: Option<String>,
+    fingerprint: Fingerprint,
+}
+
+/*
+ * The titles and authors read from the song files, so that
+ * rebuilding the index only reads the files that changed.
+ * With --data-path it's kept across restarts as well.
+*/
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct TitleCache {
+    files: HashMap<PathBuf, CachedTitle>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(skip)]
+    is_changed: bool,
+}
+
+impl TitleCache {
+    pub fn load(data_path: Option<&String>) -> Self {
+        let (mut cache, path) = state::load_json::<Self>(data_path, TITLE_CACHE_FILE);
+        cache.path = path;
+        cache
+    }
+
+    /*
+     * The title and author of the last time the file
+     * was read if it didn't change since, otherwise
+     * they are read again with read_file.
+     */
+    pub fn get(
+        &mut self,
+        song_path: &Path,
+        read_file: impl FnOnce() -> (Option<String>, Option<String>),
+    ) -> (Option<String>, Option<String>) {
+        let fingerprint = file_cache::get_fingerprint(song_path);
+        if let Some(cached) = self.files.get(song_path) {
+            if fingerprint.as_ref() == Some(&cached.fingerprint) {
+                return (cached.title.clone(), cached.author.clone());
+            }
+        }
+        let (title, author) = read_file();
+        if let Some(fingerprint) = fingerprint {
+            self.files.insert(
+                song_path.to_path_buf(),
+                CachedTitle {
+                    title: title.clone(),
+                    author: author.clone(),
+                    fingerprint,
+                },
+            );
+            self.is_changed = true;
+        }
+        (title, author)
+    }
+
+    // forgets the files that are gone and saves if anything changed
+    pub fn save(&mut self, song_paths: &HashSet<&Path>) {
+        let len = self.files.len();
+        self.files
+            .retain(|path, _| song_paths.contains(path.as_path()));
+        if !self.is_changed && self.files.len() == len {
+            return;
+        }
+        self.is_changed = false;
+        if let Some(path) = self.path.as_ref() {
+            state::save_json(path, self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    fn read(title: &str) -> (Option<String>, Option<String>) {
+        (Some(title.to_string()), None)
+    }
+
+    #[test]
+    fn files_are_only_read_again_when_they_change() {
+        let dir = env::temp_dir().join(format!("songy-titles-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Song.cho");
+        fs::write(&path, "{title: Song}").unwrap();
+        let mut cache = TitleCache::default();
+        assert_eq!(cache.get(&path, || read("Song")), read("Song"));
+        assert_eq!(cache.get(&path, || read("Other")), read("Song"));
+        fs::write(&path, "{title: Longer Song}").unwrap();
+        assert_eq!(
+            cache.get(&path, || read("Longer Song")),
+            read("Longer Song")
+        );
+        cache.save(&HashSet::new());
+        assert!(cache.files.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::fs;
+#[cfg(test)]
+use std::sync::Mutex;
+
+use bytes::Bytes;
+use frankenstein::api_params::{
+    AnswerCallbackQueryParams, AnswerInlineQueryParams, EditMessageTextParams, GetFileParams,
+    SendDocumentParams, SendMessageParams, SetMyCommandsParams,
+};
+use frankenstein::{Api, TelegramApi};
+
+pub const DEFAULT_API_URL: &str = "https://api.telegram.org";
+
+#[derive(Debug)]
+pub struct TransportError {
+    pub message: String,
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<frankenstein::Error> for TransportError {
+    fn from(err: frankenstein::Error) -> Self {
+        Self {
+            message: format!("{:?}", err),
+        }
+    }
+}
+
+impl From<reqwest::Error> for TransportError {
+    fn from(err: reqwest::Error) -> Self {
+        Self {
+            message: err.to_string(),
+        }
+    }
+}
+
+/*
+ * Everything the handlers send to or download from
+ * telegram goes through this trait so that the bot's
+ * behavior doesn't depend on a live telegram server.
+*/
+pub trait Messenger: Send + Sync {
+    fn send_message(&self, params: &SendMessageParams) -> Result<(), TransportError>;
+    // returns the file_id telegram assigned to the document
+    fn send_document(&self, params: &SendDocumentParams) -> Result<Option<String>, TransportError>;
+    fn edit_message_text(&self, params: &EditMessageTextParams) -> Result<(), TransportError>;
+    fn answer_inline_query(&self, params: &AnswerInlineQueryParams) -> Result<(), TransportError>;
+    fn answer_callback_query(
+        &self,
+        params: &AnswerCallbackQueryParams,
+    ) -> Result<(), TransportError>;
+    fn set_my_commands(&self, params: &SetMyCommandsParams) -> Result<(), TransportError>;
+    fn download_file(&self, file_id: &str) -> Result<Bytes, TransportError>;
+}
+
+pub struct Telegram {
+    api: Api,
+    token: String,
+    // without ending slash, e.g. https://api.telegram.org
+    api_url: String,
+}
+
+impl Telegram {
+    pub fn new(api: Api, token: String, api_url: &str) -> Self {
+        Self {
+            api,
+            token,
+            api_url: api_url.to_string(),
+        }
+    }
+}
+
+impl Messenger for Telegram {
+    fn send_message(&self, params: &SendMessageParams) -> Result<(), TransportError> {
+        self.api.send_message(params)?;
+        Ok(())
+    }
+
+    fn send_document(&self, params: &SendDocumentParams) -> Result<Option<String>, TransportError> {
+        let res = self.api.send_document(params)?;
+        Ok(res.result.document.map(|document| document.file_id))
+    }
+
+    fn edit_message_text(&self, params: &EditMessageTextParams) -> Result<(), TransportError> {
+        self.api.edit_message_text(params)?;
+        Ok(())
+    }
+
+    fn answer_inline_query(&self, params: &AnswerInlineQueryParams) -> Result<(), TransportError> {
+        self.api.answer_inline_query(params)?;
+        Ok(())
+    }
+
+    fn answer_callback_query(
+        &self,
+        params: &AnswerCallbackQueryParams,
+    ) -> Result<(), TransportError> {
+        self.api.answer_callback_query(params)?;
+        Ok(())
+    }
+
+    fn set_my_commands(&self, params: &SetMyCommandsParams) -> Result<(), TransportError> {
+        self.api.set_my_commands(params)?;
+        Ok(())
+    }
+
+    fn download_file(&self, file_id: &str) -> Result<Bytes, TransportError> {
+        let file = self.api.get_file(&GetFileParams {
+            file_id: file_id.to_string(),
+        })?;
+        let file_path = match file.result.file_path {
+            Some(file_path) => file_path,
+            None => {
+                return Err(TransportError {
+                    message: String::from("File has no file_path."),
+                })
+            }
+        };
+        // a local bot api server started with --local returns the absolute path on its disk
+        if file_path.starts_with('/') {
+            let bytes = fs::read(&file_path).map_err(|err| TransportError {
+                message: format!("Cannot read {}: {}", file_path, err),
+            })?;
+            return Ok(Bytes::from(bytes));
+        }
+        let url = format!("{}/file/bot{}/{}", self.api_url, self.token, file_path);
+        let bytes = reqwest::blocking::get(url)?.bytes()?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+pub enum Sent {
+    Message(SendMessageParams),
+    Document(SendDocumentParams),
+    EditedMessage(EditMessageTextParams),
+    InlineQueryAnswer(AnswerInlineQueryParams),
+    CallbackQueryAnswer(AnswerCallbackQueryParams),
+    Commands(SetMyCommandsParams),
+}
+
+/*
+ * Keeps everything the bot sends in memory
+ * instead of sending it to telegram, for tests.
+*/
+#[cfg(test)]
+pub struct Recorder {
+    sent: Mutex<Vec<Sent>>,
+}
+
+#[cfg(test)]
+impl Recorder {
+    pub fn new() -> Self {
+        Self {
+            sent: Mutex::new(vec![]),
+        }
+    }
+
+    // returns everything sent since the last call
+    pub fn take_sent(&self) -> Vec<Sent> {
+        std::mem::take(&mut *self.sent.lock().unwrap())
+    }
+
+    fn record(&self, sent: Sent) {
+        self.sent.lock().unwrap().push(sent);
+    }
+}
+
+#[cfg(test)]
+impl Messenger for Recorder {
+    fn send_message(&self, params: &SendMessageParams) -> Result<(), TransportError> {
+        self.record(Sent::Message(params.clone()));
+        Ok(())
+    }
+
+    fn send_document(&self, params: &SendDocumentParams) -> Result<Option<String>, TransportError> {
+        self.record(Sent::Document(params.clone()));
+        Ok(None)
+    }
+
+    fn edit_message_text(&self, params: &EditMessageTextParams) -> Result<(), TransportError> {
+        self.record(Sent::EditedMessage(params.clone()));
+        Ok(())
+    }
+
+    fn answer_inline_query(&self, params: &AnswerInlineQueryParams) -> Result<(), TransportError> {
+        self.record(Sent::InlineQueryAnswer(params.clone()));
+        Ok(())
+    }
+
+    fn answer_callback_query(
+        &self,
+        params: &AnswerCallbackQueryParams,
+    ) -> Result<(), TransportError> {
+        self.record(Sent::CallbackQueryAnswer(params.clone()));
+        Ok(())
+    }
+
+    fn set_my_commands(&self, params: &SetMyCommandsParams) -> Result<(), TransportError> {
+        self.record(Sent::Commands(params.clone()));
+        Ok(())
+    }
+
+    fn download_file(&self, file_id: &str) -> Result<Bytes, TransportError> {
+        Err(TransportError {
+            message: format!("Can't download {} without telegram.", file_id),
+        })
+    }
+}
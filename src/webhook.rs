@@ -0,0 +1,199 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use frankenstein::api_params::SetWebhookParams;
+use frankenstein::objects::Update;
+use frankenstein::{Api, TelegramApi};
+
+use crate::workers::Workers;
+use crate::{Config, HandleArg};
+
+const SECRET_HEADER: &str = "x-telegram-bot-api-secret-token";
+// updates are small json objects, everything bigger isn't from telegram
+const MAX_BODY_LEN: usize = 1024 * 1024;
+const MAX_HEADER_LEN: usize = 16 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/*
+ * Requests are read on a thread each, so that a slow
+ * client doesn't hold up telegram. Beyond this many at
+ * once new connections are turned away right away.
+*/
+const MAX_CONNECTIONS: usize = 16;
+
+// what a request must match to be taken as an update
+struct Endpoint {
+    path: String,
+    secret: String,
+}
+
+/*
+ * Telegram posts every update to the --webhook-url.
+ * songy only speaks plain http, https is up to
+ * the reverse proxy forwarding to --webhook-listen.
+*/
+pub fn run(api: &Api, handle_arg: &mut HandleArg, workers: &Workers, config: &Config) {
+    let listen = config.webhook_listen.clone().unwrap();
+    let listener = match TcpListener::bind(&listen) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Cannot listen on {}.", listen);
+            dbg!(err);
+            process::exit(-1);
+        }
+    };
+    let secret = config
+        .webhook_secret
+        .clone()
+        .unwrap_or_else(generate_secret);
+    let params = SetWebhookParams::builder()
+        .url(config.webhook_url.clone().unwrap())
+        .allowed_updates(crate::ALLOWED_UPDATES.to_vec())
+        .secret_token(secret.clone())
+        .build();
+    if let Err(err) = api.set_webhook(&params) {
+        eprintln!("set_webhook failed.");
+        dbg!(err);
+        process::exit(-1);
+    }
+    let endpoint = Endpoint {
+        path: get_path(config.webhook_url.as_ref().unwrap()),
+        secret,
+    };
+    let (sender, receiver) = mpsc::channel::<Update>();
+    thread::spawn(move || accept(listener, endpoint, sender));
+    for update in receiver {
+        crate::refresh_index(handle_arg);
+        workers.dispatch(handle_arg.clone(), update);
+    }
+}
+
+fn accept(listener: TcpListener, endpoint: Endpoint, sender: Sender<Update>) {
+    let endpoint = Arc::new(endpoint);
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Accepting webhook connection failed.");
+                dbg!(err);
+                continue;
+            }
+        };
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            respond(&mut stream, "503 Service Unavailable");
+            continue;
+        }
+        let endpoint = endpoint.clone();
+        let connections = connections.clone();
+        let sender = sender.clone();
+        thread::spawn(move || {
+            let update = read_update(&mut stream, &endpoint);
+            let status = match update {
+                Ok(_) => "200 OK",
+                Err(status) => status,
+            };
+            respond(&mut stream, status);
+            drop(stream);
+            connections.fetch_sub(1, Ordering::SeqCst);
+            if let Ok(update) = update {
+                let _ = sender.send(update);
+            }
+        });
+    }
+}
+
+// the path and query of the url, telegram posts to exactly that
+fn get_path(url: &str) -> String {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    match without_scheme.find(['/', '?']) {
+        Some(i) if without_scheme[i..].starts_with('/') => without_scheme[i..].to_string(),
+        Some(i) => format!("/{}", &without_scheme[i..]),
+        None => String::from("/"),
+    }
+}
+
+/*
+ * Returns the http status to answer with
+ * if the request isn't a valid update.
+*/
+fn read_update(stream: &mut TcpStream, endpoint: &Endpoint) -> Result<Update, &'static str> {
+    if stream.set_read_timeout(Some(READ_TIMEOUT)).is_err() {
+        return Err("500 Internal Server Error");
+    }
+    let mut reader = BufReader::new(stream.take((MAX_HEADER_LEN + MAX_BODY_LEN) as u64));
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return Err("400 Bad Request");
+    }
+    let mut parts = request_line.split_whitespace();
+    if parts.next() != Some("POST") {
+        return Err("405 Method Not Allowed");
+    }
+    if parts.next() != Some(endpoint.path.as_str()) {
+        return Err("404 Not Found");
+    }
+    let mut content_length: Option<usize> = None;
+    let mut is_secret_valid = false;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).is_err() {
+            return Err("400 Bad Request");
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err("400 Bad Request");
+        };
+        match name.trim().to_lowercase().as_str() {
+            "content-length" => content_length = value.trim().parse().ok(),
+            SECRET_HEADER => is_secret_valid = value.trim() == endpoint.secret,
+            _ => {}
+        }
+    }
+    if !is_secret_valid {
+        return Err("403 Forbidden");
+    }
+    let content_length = match content_length {
+        Some(len) if len > MAX_BODY_LEN => return Err("413 Payload Too Large"),
+        Some(len) => len,
+        None => return Err("411 Length Required"),
+    };
+    let mut body = vec![0; content_length];
+    if reader.read_exact(&mut body).is_err() {
+        return Err("400 Bad Request");
+    }
+    serde_json::from_slice(&body).map_err(|_| "400 Bad Request")
+}
+
+fn respond(stream: &mut TcpStream, status: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        status
+    );
+    if let Err(err) = stream.write_all(response.as_bytes()) {
+        eprintln!("Answering webhook request failed.");
+        dbg!(err);
+    }
+}
+
+/*
+ * The secret only has to be known to telegram,
+ * a new one is set with every start.
+ * RandomState has random keys from the os.
+*/
+fn generate_secret() -> String {
+    (0..4)
+        .map(|_| format!("{:016x}", RandomState::new().build_hasher().finish()))
+        .collect()
+}
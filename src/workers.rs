@@ -0,0 +1,125 @@
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use frankenstein::objects::{Update, UpdateContent};
+
+use crate::HandleArg;
+
+/*
+ * How many updates may wait for each worker
+ * before receiving new updates is paused.
+*/
+const QUEUE_LEN: usize = 100;
+
+/*
+ * Handles updates on several threads at once so that
+ * one slow upload doesn't keep everyone else waiting.
+ * All updates of a chat go to the same worker,
+ * so they are still handled in the order they arrived.
+*/
+pub struct Workers {
+    senders: Vec<SyncSender<(HandleArg, Update)>>,
+    pending: Arc<Mutex<Pending>>,
+}
+
+/*
+ * The update_ids in the order they were dispatched, with
+ * whether they have been handled. The saved offset is the
+ * first one that hasn't, so that after a restart getUpdates
+ * returns every update that was still waiting in a queue.
+*/
+#[derive(Default)]
+struct Pending {
+    updates: VecDeque<(u32, bool)>,
+    // the offset is the one after it once everything is handled
+    last_dispatched: Option<u32>,
+}
+
+impl Workers {
+    pub fn new(count: usize) -> Self {
+        let mut senders = vec![];
+        let pending = Arc::new(Mutex::new(Pending::default()));
+        for _ in 0..count {
+            let (sender, receiver) = mpsc::sync_channel::<(HandleArg, Update)>(QUEUE_LEN);
+            let pending = pending.clone();
+            thread::spawn(move || {
+                for (mut handle_arg, update) in receiver {
+                    // a panic while handling one update shouldn't stop the worker
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        crate::handle_update(&mut handle_arg, &update)
+                    }));
+                    if result.is_err() {
+                        eprintln!("Handling update {} failed.", update.update_id);
+                    }
+                    finish(&pending, &handle_arg, update.update_id);
+                }
+            });
+            senders.push(sender);
+        }
+        Self { senders, pending }
+    }
+
+    /*
+     * Updates that are waiting, being handled or were handled
+     * while getUpdates was running are received again, they are
+     * skipped. Telegram's update_ids only ever increase.
+     */
+    pub fn dispatch(&self, handle_arg: HandleArg, update: Update) {
+        let mut pending = crate::lock(&self.pending);
+        if pending
+            .last_dispatched
+            .is_some_and(|id| update.update_id <= id)
+        {
+            return;
+        }
+        pending.updates.push_back((update.update_id, false));
+        pending.last_dispatched = Some(update.update_id);
+        drop(pending);
+        let i = get_chat_key(&update) as usize % self.senders.len();
+        if self.senders[i].send((handle_arg, update)).is_err() {
+            eprintln!("Dispatching update failed.");
+        }
+    }
+}
+
+/*
+ * Marks the update as handled and saves the offset
+ * of the first update that hasn't been handled yet.
+*/
+fn finish(pending: &Mutex<Pending>, handle_arg: &HandleArg, update_id: u32) {
+    let mut pending = crate::lock(pending);
+    if let Some(update) = pending.updates.iter_mut().find(|(id, _)| *id == update_id) {
+        update.1 = true;
+    }
+    while pending.updates.front().is_some_and(|(_, handled)| *handled) {
+        pending.updates.pop_front();
+    }
+    let offset = match pending.updates.front() {
+        Some((id, _)) => Some(i64::from(*id)),
+        None => pending.last_dispatched.map(|id| i64::from(id) + 1),
+    };
+    let mut state = crate::lock(&handle_arg.state);
+    if state.offset != offset {
+        state.offset = offset;
+        state.save();
+    }
+}
+
+/*
+ * Inline queries don't belong to a chat,
+ * they are kept in order per user instead.
+*/
+fn get_chat_key(update: &Update) -> u64 {
+    match &update.content {
+        UpdateContent::Message(msg) => msg.chat.id.unsigned_abs(),
+        UpdateContent::CallbackQuery(query) => match query.message.as_ref() {
+            Some(msg) => msg.chat.id.unsigned_abs(),
+            None => query.from.id,
+        },
+        UpdateContent::InlineQuery(query) => query.from.id,
+        _ => 0,
+    }
+}
@@ -0,0 +1,231 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command};
+use std::time::{Duration, Instant};
+use std::{env, fs, thread};
+
+mod local_bot_api;
+use local_bot_api::{LocalBotApi, VOICE_BYTES, VOICE_FILE_PATH};
+
+const TOKEN: &str = "test";
+
+/*
+ * songy polling the stand-in, it's
+ * killed at the end of the test.
+*/
+struct Songy {
+    child: Child,
+    reports_path: PathBuf,
+}
+
+impl Songy {
+    // the files are created empty, relative to the songs folder
+    fn start(name: &str, api: &LocalBotApi, files: &[&str]) -> Self {
+        Self::start_with_args(name, api, files, &[])
+    }
+
+    fn start_with_args(name: &str, api: &LocalBotApi, files: &[&str], args: &[&str]) -> Self {
+        let dir = env::temp_dir().join(format!("songy-e2e-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        let songs_path = dir.join("songs");
+        let reports_path = dir.join("reports");
+        fs::create_dir_all(&reports_path).unwrap();
+        for file in files {
+            let path = songs_path.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "%PDF-1.4").unwrap();
+        }
+        let child = Command::new(env!("CARGO_BIN_EXE_songy"))
+            .arg("--api-url")
+            .arg(&api.url)
+            .arg("--token")
+            .arg(TOKEN)
+            .arg("--songs-path")
+            .arg(&songs_path)
+            .arg("--reports-path")
+            .arg(&reports_path)
+            .args(args)
+            .spawn()
+            .unwrap();
+        Self {
+            child,
+            reports_path,
+        }
+    }
+
+    fn reports(&self) -> Vec<PathBuf> {
+        fs::read_dir(&self.reports_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect()
+    }
+}
+
+impl Drop for Songy {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle.as_bytes())
+}
+
+fn file_name(path: &Path) -> &str {
+    path.file_name().unwrap().to_str().unwrap()
+}
+
+// an address nothing listens on yet
+fn get_free_address() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+fn connect(address: &str) -> TcpStream {
+    let start = Instant::now();
+    loop {
+        match TcpStream::connect(address) {
+            Ok(stream) => return stream,
+            Err(_) if start.elapsed() < Duration::from_secs(20) => {
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(err) => panic!("songy doesn't listen: {}", err),
+        }
+    }
+}
+
+// returns the status line of the answer
+fn post(address: &str, path: &str, secret: &str, body: &str) -> String {
+    let mut stream = connect(address);
+    let request = format!(
+        "POST {} HTTP/1.1\r\nX-Telegram-Bot-Api-Secret-Token: {}\r\nContent-Length: {}\r\n\r\n{}",
+        path,
+        secret,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response.lines().next().unwrap_or("").to_string()
+}
+
+#[test]
+fn messages_are_answered_with_messages_and_documents() {
+    let api = LocalBotApi::start();
+    let _songy = Songy::start(
+        "answers",
+        &api,
+        &["en/Amazing_Grace.pdf", "en/Love_Me_Tender.pdf"],
+    );
+    api.send_text("/start");
+    let request = api.wait_for("sendMessage");
+    assert!(request.params()["text"].as_str().unwrap().contains("/list"));
+
+    api.send_text("/Amazing_Grace");
+    let request = api.wait_for("sendDocument");
+    // the first time the file is uploaded
+    assert!(request.content_type.starts_with("multipart/form-data"));
+    assert!(contains(&request.body, "Amazing_Grace.pdf"));
+
+    api.send_text("/list");
+    let request = api.wait_for("sendMessage");
+    let params = request.params();
+    assert!(params["text"].as_str().unwrap().contains("/Love_Me_Tender"));
+    let data = params["reply_markup"]["inline_keyboard"][0][0]["callback_data"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    api.press(&data);
+    api.wait_for("answerCallbackQuery");
+    let request = api.wait_for("sendDocument");
+    // then the file_id telegram returned for the upload is sent instead
+    assert!(request.params()["document"]
+        .as_str()
+        .unwrap()
+        .starts_with("document_"));
+}
+
+#[test]
+fn voice_reports_are_downloaded() {
+    let api = LocalBotApi::start();
+    let songy = Songy::start("voice", &api, &["en/Amazing_Grace.pdf"]);
+    api.send_text("/report");
+    api.wait_for("sendMessage");
+    api.send_voice();
+    api.wait_for("getFile");
+    let request = api.wait_for("file");
+    assert_eq!(
+        request.path,
+        format!("/file/bot{}/{}", TOKEN, VOICE_FILE_PATH)
+    );
+    api.wait_for("sendMessage");
+    let reports = songy.reports();
+    assert_eq!(reports.len(), 1);
+    assert!(file_name(&reports[0]).ends_with(".ogg"));
+    assert_eq!(fs::read(&reports[0]).unwrap(), VOICE_BYTES);
+}
+
+#[test]
+fn webhook_is_not_held_up_by_slow_clients() {
+    let api = LocalBotApi::start();
+    let address = get_free_address();
+    let _songy = Songy::start_with_args(
+        "webhook",
+        &api,
+        &["en/Amazing_Grace.pdf"],
+        &[
+            "--webhook-url",
+            "https://example.org/songy",
+            "--webhook-listen",
+            &address,
+            "--webhook-secret",
+            "secret",
+        ],
+    );
+    api.wait_for("setWebhook");
+    // connects and never sends anything
+    let _slow_client = connect(&address);
+    let update = api.get_text_update("/start").to_string();
+    let start = Instant::now();
+    assert_eq!(
+        post(&address, "/other", "secret", &update),
+        "HTTP/1.1 404 Not Found"
+    );
+    assert_eq!(
+        post(&address, "/songy", "wrong", &update),
+        "HTTP/1.1 403 Forbidden"
+    );
+    assert_eq!(
+        post(&address, "/songy", "secret", &update),
+        "HTTP/1.1 200 OK"
+    );
+    let request = api.wait_for("sendMessage");
+    assert!(request.params()["text"].as_str().unwrap().contains("/list"));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn check_writes_nothing() {
+    let dir = env::temp_dir().join(format!("songy-e2e-{}-check", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let songs_path = dir.join("songs");
+    let data_path = dir.join("data");
+    fs::create_dir_all(&songs_path).unwrap();
+    fs::create_dir_all(&data_path).unwrap();
+    fs::write(songs_path.join("Été.pdf"), "%PDF-1.4").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_songy"))
+        .arg("--songs-path")
+        .arg(&songs_path)
+        .arg("--data-path")
+        .arg(&data_path)
+        .arg("check")
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("/Ete"));
+    assert_eq!(fs::read_dir(&data_path).unwrap().count(), 0);
+}
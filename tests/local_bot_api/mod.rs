@@ -0,0 +1,293 @@
+/*
+ * A small stand-in for the telegram bot api, so that
+ * songy can be tested end to end without telegram.
+ * It listens on a free port of 127.0.0.1, hands out the
+ * updates the test added to getUpdates and records
+ * every request songy makes.
+*/
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+const USER_ID: u64 = 1;
+const VOICE_FILE_ID: &str = "voice";
+pub const VOICE_FILE_PATH: &str = "voice/file_0.oga";
+// what a downloaded voice message contains
+pub const VOICE_BYTES: &[u8] = b"OggS";
+
+pub struct Request {
+    // e.g. "sendMessage" or "file" for downloads
+    pub method: String,
+    pub path: String,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    // the parameters of a json request
+    pub fn params(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(json!({}))
+    }
+}
+
+#[derive(Default)]
+struct State {
+    updates: VecDeque<Value>,
+    requests: Vec<Request>,
+    next_update_id: u64,
+    next_message_id: u64,
+}
+
+impl State {
+    fn add_update(&mut self, mut update: Value) {
+        update["update_id"] = json!(self.next_update_id);
+        self.next_update_id += 1;
+        self.updates.push_back(update);
+    }
+
+    fn next_message_id(&mut self) -> u64 {
+        self.next_message_id += 1;
+        self.next_message_id
+    }
+}
+
+pub struct LocalBotApi {
+    pub url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl LocalBotApi {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(State::default()));
+        let server_state = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = server_state.clone();
+                thread::spawn(move || handle_connection(stream, state));
+            }
+        });
+        Self { url, state }
+    }
+
+    pub fn send_text(&self, text: &str) {
+        let mut state = self.state.lock().unwrap();
+        let mut message = get_message(state.next_message_id());
+        message["text"] = json!(text);
+        state.add_update(json!({ "message": message }));
+    }
+
+    // an update that is posted to songy's webhook instead
+    pub fn get_text_update(&self, text: &str) -> Value {
+        let mut state = self.state.lock().unwrap();
+        let mut message = get_message(state.next_message_id());
+        message["text"] = json!(text);
+        let update_id = state.next_update_id;
+        state.next_update_id += 1;
+        json!({ "update_id": update_id, "message": message })
+    }
+
+    pub fn send_voice(&self) {
+        let mut state = self.state.lock().unwrap();
+        let mut message = get_message(state.next_message_id());
+        message["voice"] = json!({
+            "file_id": VOICE_FILE_ID,
+            "file_unique_id": VOICE_FILE_ID,
+            "duration": 1,
+        });
+        state.add_update(json!({ "message": message }));
+    }
+
+    // a press on an inline keyboard button
+    pub fn press(&self, data: &str) {
+        let mut state = self.state.lock().unwrap();
+        let message_id = state.next_message_id();
+        state.add_update(json!({
+            "callback_query": {
+                "id": message_id.to_string(),
+                "from": get_user(),
+                "chat_instance": "0",
+                "data": data,
+                "message": get_message(message_id),
+            }
+        }));
+    }
+
+    /*
+     * Waits for the next request of the method, the
+     * requests before it are skipped and dropped.
+     */
+    pub fn wait_for(&self, method: &str) -> Request {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(20) {
+            let mut state = self.state.lock().unwrap();
+            if let Some(i) = state
+                .requests
+                .iter()
+                .position(|request| request.method == method)
+            {
+                return state.requests.drain(..=i).next_back().unwrap();
+            }
+            drop(state);
+            thread::sleep(Duration::from_millis(50));
+        }
+        panic!("songy didn't call {}", method);
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, state: Arc<Mutex<State>>) {
+    let Some(request) = read_request(&mut stream) else {
+        return;
+    };
+    let mut state = state.lock().unwrap();
+    let (status, body) = if request.method == "file" {
+        ("200 OK", VOICE_BYTES.to_vec())
+    } else {
+        match get_result(&request, &mut state) {
+            Some(result) => (
+                "200 OK",
+                json!({ "ok": true, "result": result })
+                    .to_string()
+                    .into_bytes(),
+            ),
+            None => (
+                "404 Not Found",
+                json!({ "ok": false, "error_code": 404, "description": "Not Found" })
+                    .to_string()
+                    .into_bytes(),
+            ),
+        }
+    };
+    // getUpdates is polled all the time, only the answers are of interest
+    if request.method != "getUpdates" {
+        state.requests.push(request);
+    }
+    drop(state);
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    let _ = stream
+        .write_all(header.as_bytes())
+        .and_then(|_| stream.write_all(&body));
+}
+
+fn get_result(request: &Request, state: &mut State) -> Option<Value> {
+    let params = request.params();
+    match request.method.as_str() {
+        "getMe" => Some(json!({
+            "id": 0,
+            "is_bot": true,
+            "first_name": "songy",
+            "username": "songybot",
+        })),
+        "getUpdates" => {
+            let offset = params["offset"].as_u64().unwrap_or(0);
+            state
+                .updates
+                .retain(|update| update["update_id"].as_u64().unwrap() >= offset);
+            Some(Value::Array(state.updates.iter().cloned().collect()))
+        }
+        "getFile" => Some(json!({
+            "file_id": VOICE_FILE_ID,
+            "file_unique_id": VOICE_FILE_ID,
+            "file_path": VOICE_FILE_PATH,
+        })),
+        "sendMessage" | "editMessageText" => {
+            let mut message = get_message(state.next_message_id());
+            message["text"] = params["text"].clone();
+            Some(message)
+        }
+        "sendDocument" => {
+            let message_id = state.next_message_id();
+            let mut message = get_message(message_id);
+            let file_id = format!("document_{}", message_id);
+            message["document"] = json!({ "file_id": file_id, "file_unique_id": file_id });
+            Some(message)
+        }
+        "answerInlineQuery"
+        | "answerCallbackQuery"
+        | "setMyCommands"
+        | "setWebhook"
+        | "deleteWebhook" => Some(json!(true)),
+        _ => None,
+    }
+}
+
+fn get_user() -> Value {
+    json!({ "id": USER_ID, "is_bot": false, "first_name": "test" })
+}
+
+fn get_message(message_id: u64) -> Value {
+    json!({
+        "message_id": message_id,
+        "date": 0,
+        "chat": { "id": USER_ID, "type": "private" },
+        "from": get_user(),
+    })
+}
+
+/*
+ * Only as much HTTP/1.1 as the bot's http client uses:
+ * bodies with Content-Length or chunked encoding.
+*/
+fn read_request(stream: &mut TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let path = request_line.split(' ').nth(1)?.to_string();
+    let method = if path.starts_with("/file/") {
+        String::from("file")
+    } else {
+        path.rsplit('/').next().unwrap_or("").to_string()
+    };
+    let mut content_length = 0;
+    let mut chunked = false;
+    let mut content_type = String::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        let value = value.trim();
+        match name.to_lowercase().as_str() {
+            "content-length" => content_length = value.parse().ok()?,
+            "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+            "content-type" => content_type = value.to_string(),
+            _ => {}
+        }
+    }
+    let mut body = vec![];
+    if chunked {
+        loop {
+            let mut size_line = String::new();
+            reader.read_line(&mut size_line).ok()?;
+            let size = usize::from_str_radix(size_line.trim(), 16).ok()?;
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk).ok()?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else {
+        body.resize(content_length, 0);
+        reader.read_exact(&mut body).ok()?;
+    }
+    Some(Request {
+        method,
+        path,
+        content_type,
+        body,
+    })
+}